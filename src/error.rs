@@ -29,6 +29,88 @@ pub enum SplStoreError {
     UnexpectedAtaAddress,
     #[error("Account is not store administrator")]
     AccountNotAdmin,
+    #[error("Store is already initialized")]
+    StoreAlreadyInitialized,
+    #[error("Store is not initialized")]
+    StoreNotInitialized,
+    #[error("Store administrator did not sign the transaction")]
+    AdminNotSigner,
+    #[error("No admin handover is pending")]
+    NoPendingAdmin,
+    #[error("Account is not the pending store administrator")]
+    AccountNotPendingAdmin,
+    #[error("Invalid multisig signers or threshold")]
+    InvalidMultisigConfig,
+    #[error("Not enough multisig signers")]
+    NotEnoughMultisigSigners,
+    #[error("Account is neither store administrator nor holds the required role")]
+    AccountLacksRole,
+    #[error("Trading in this direction is paused")]
+    StorePaused,
+    #[error("Withdrawal would leave the account below its rent exempt minimum")]
+    WithdrawalBreaksRentExemption,
+    #[error("Store account is not at the address derived from its creator, mint and index")]
+    InvalidStoreAddress,
+    #[error("Vault account is not the one derived from the store")]
+    InvalidVaultAddress,
+    #[error("Mint account is not the store's token mint")]
+    StoreMintMismatch,
+    #[error("Mint is not listed by the store")]
+    MintNotListed,
+    #[error("Mint is already listed by the store")]
+    MintAlreadyListed,
+    #[error("Store catalog is full")]
+    CatalogFull,
+    #[error("Trading of this mint is disabled")]
+    MintDisabled,
+    #[error("Bid price exceeds ask price")]
+    BidExceedsAsk,
+    #[error("Arithmetic overflow")]
+    MathOverflow,
+    #[error("Trade price moved beyond the client's limit")]
+    SlippageExceeded,
+    #[error("Store has no quote signer")]
+    QuoteSignerNotSet,
+    #[error("Quote has expired")]
+    QuoteExpired,
+    #[error("Quote nonce was already used")]
+    QuoteNonceUsed,
+    #[error("Quote is not signed by the store's quote signer")]
+    InvalidQuoteSignature,
+    #[error("Quote mint cannot be a mint the store trades")]
+    InvalidQuoteMint,
+    #[error("Mint account is not the store's quote mint")]
+    QuoteMintMismatch,
+    #[error("Store settles trades in its quote mint, not in wrapped SOL")]
+    WrappedSolUnavailable,
+    #[error("Fee exceeds 10000 basis points")]
+    InvalidFee,
+    #[error("Store holds fees that were not collected yet")]
+    UnclaimedFees,
+    #[error("Account is not the store's fee recipient")]
+    FeeRecipientMismatch,
+    #[error("Referral share exceeds 10000 basis points")]
+    InvalidReferralShare,
+    #[error("Bonding curve needs a step interval and exponential curves a growth rate")]
+    InvalidCurve,
+    #[error("Store token is priced by a bonding curve")]
+    PricedByCurve,
+    #[error("Store cannot buy back more tokens than it sold on its bonding curve")]
+    CurveSupplyExceeded,
+    #[error("Store token is priced by the store's reserves")]
+    PricedByReserves,
+    #[error("Store reserves are empty or cannot cover the trade")]
+    InsufficientReserves,
+    #[error("Catalog mints have to be removed before closing the store")]
+    CatalogNotEmpty,
+    #[error("Store still holds tokens of its quote mint")]
+    QuoteMintNotWithdrawn,
+    #[error("Authority did not sign the transaction")]
+    AuthorityNotSigner,
+    #[error("Store still holds tokens of the catalog mint")]
+    CatalogMintNotWithdrawn,
+    #[error("Referrer cannot be the store vault or a party to the trade")]
+    InvalidReferrer,
 }
 
 impl From<SplStoreError> for ProgramError {
@@ -1,40 +1,275 @@
 use borsh_derive::{BorshDeserialize, BorshSerialize};
+use spl_token::solana_program::pubkey::Pubkey;
 
-use crate::store::{Amount, Price};
+use crate::store::{curve::PricingMode, prices::Prices, quote::Quote, role::Role, Amount, Price};
 
 #[derive(Debug, BorshDeserialize, BorshSerialize)]
 pub enum SplStoreInstruction {
-    /// Initialize store and create ATA
+    /// Initialize the store with the given index and create its ATA,
+    /// fails if the store is already initialized
     /// - \[writeable, signer] Funding account
     /// - \[writeable] Store ATA
-    /// - [] Store account - wallet address
+    /// - \[writeable] Store account - PDA of ("store", admin, mint, index)
     /// - [] Token Mint account
     /// - [] System program account
-    /// - [] SPL Token program account
-    /// - [] Store admin account
-    Initialize(Price, Amount),
-    /// Buy tokens from a client
+    /// - [] SPL Token or Token-2022 program account, owner of the mint
+    /// - [signer] Store admin account
+    /// - \[writeable] Store vault account - PDA of ("vault", store), receives the initial SOL
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    Initialize(Prices, Amount, u64),
+    /// Buy tokens of the store's own or a catalog mint from a client, paying in SOL or,
+    /// if the store has one, in its quote mint. The client is paid for the tokens the
+    /// store receives, that is without a transfer fee charged by the mint, less the store fee
     /// - \[writeable, signer] Funding account - for ATA
-    /// - \[writeable] Store account (sol source) - wallet
+    /// - \[writeable] Store account - only has to be writeable if the store charges a fee
+    ///   or prices its token on a curve
+    /// - \[writeable] Store vault account (sol source)
     /// - \[writeable] Store ATA (token recipient)
     /// - \[writeable, signer] Client account (sol recipient) - ATA's order/delegate
     /// - \[writeable] Client ATA (token source)
     /// - [] Token Mint account
     /// - [] System program account
-    /// - [] SPL Token Program account
-    Buy(Amount),
-    /// Sell tokens to a client
+    /// - [] SPL Token or Token-2022 program account, owner of the mint
+    /// - \[writeable] Store ATA of the quote mint (quote source), only with a quote mint
+    /// - \[writeable] Client ATA of the quote mint (quote recipient), only with a quote mint
+    /// - [] Quote mint account, only with a quote mint
+    /// - [] SPL Token or Token-2022 program account, owner of the quote mint, only with
+    ///   a quote mint
+    /// - \[writeable] Referrer account, optional, after all other accounts of the trade -
+    ///   receives the referral share of the fee in lamports or, with a quote mint,
+    ///   is a token account of the quote mint
+    Buy {
+        amount: Amount,
+        /// Fewest lamports, or quote mint base units after its transfer fee, the client
+        /// accepts for the tokens
+        min_lamports: Amount,
+    },
+    /// Sell tokens of the store's own or a catalog mint to a client, charging SOL or,
+    /// if the store has one, its quote mint. The client pays for the tokens leaving the
+    /// store plus the store fee, a transfer fee charged by the mint is withheld from what
+    /// it receives. A transfer fee charged by the quote mint is paid on top
     /// - \[writeable, signer] Funding account - for ATA
-    /// - \[writeable, signer] Store account (sol recipient) - ATA's order/delegate
+    /// - \[writeable] Store account - ATA's owner, signed by the program, only has to be
+    ///   writeable if the store charges a fee or prices its token on a curve
+    /// - \[writeable] Store vault account (sol recipient)
     /// - \[writeable] Store ATA (token source)
-    /// - \[writeable] Client account (sol source) - wallet
+    /// - \[writeable, signer] Client account (sol source) - system-owned wallet
     /// - \[writeable] Client ATA (token recipient)
     /// - [] Token Mint account
     /// - [] System program account
-    /// - [] Token Program account
-    Sell(Amount),
-    /// Update token price
+    /// - [] SPL Token or Token-2022 program account, owner of the mint
+    /// - \[writeable] Store ATA of the quote mint (quote recipient), only with a quote mint
+    /// - \[writeable] Client ATA of the quote mint (quote source), only with a quote mint
+    /// - [] Quote mint account, only with a quote mint
+    /// - [] SPL Token or Token-2022 program account, owner of the quote mint, only with
+    ///   a quote mint
+    /// - \[writeable] Referrer account, optional, after all other accounts of the trade -
+    ///   receives the referral share of the fee in lamports or, with a quote mint,
+    ///   is a token account of the quote mint
+    Sell {
+        amount: Amount,
+        /// Most lamports, or quote mint base units, the client agrees to pay for the tokens
+        max_lamports: Amount,
+    },
+    /// Update the bid and/or ask price of the store's own token, the bid may not exceed the ask
     /// - \[writeable] Store account
-    /// - [] Admin account
-    UpdatePrice(Price),
+    /// - [signer] Admin or pricer account
+    /// - [signer] M multisig signer accounts, if the authority is a multisig
+    UpdatePrice {
+        bid: Option<Price>,
+        ask: Option<Price>,
+    },
+    /// Nominate a new store admin, replacing any pending nomination
+    /// - \[writeable] Store account
+    /// - [signer] Admin account
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    ProposeAdmin(Pubkey),
+    /// Accept a pending nomination and become the store admin
+    /// - \[writeable] Store account
+    /// - [signer] Pending admin account
+    /// - [signer] M multisig signer accounts, if the pending admin is a multisig
+    AcceptAdmin,
+    /// Withdraw a pending admin nomination
+    /// - \[writeable] Store account
+    /// - [signer] Admin account
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    CancelAdminProposal,
+    /// Create an M-of-N multisig that can be used as a store admin
+    /// - \[writeable, signer] Funding account
+    /// - \[writeable, signer] New multisig account
+    /// - [] System program account
+    /// - [] N distinct signer accounts, at most 11
+    InitializeMultisig(u8),
+    /// Assign a role to an account, replacing its previous holder.
+    /// Only the admin may assign the role manager
+    /// - \[writeable] Store account
+    /// - [signer] Admin or role manager account
+    /// - [signer] M multisig signer accounts, if the authority is a multisig
+    GrantRole(Role, Pubkey),
+    /// Remove the holder of a role.
+    /// Only the admin may revoke the role manager
+    /// - \[writeable] Store account
+    /// - [signer] Admin or role manager account
+    /// - [signer] M multisig signer accounts, if the authority is a multisig
+    RevokeRole(Role),
+    /// Halt or resume trading in each direction
+    /// - \[writeable] Store account
+    /// - [signer] Admin or pauser account
+    /// - [signer] M multisig signer accounts, if the authority is a multisig
+    SetPaused { buy: bool, sell: bool },
+    /// Move remaining tokens out, close the store ATA, the vault and the store account.
+    /// Catalog mints have to be removed, the quote mint withdrawn and fees collected
+    /// beforehand
+    /// - \[writeable] Store account
+    /// - \[writeable] Store vault account
+    /// - \[writeable] Store ATA
+    /// - \[writeable] Token destination account, only used if the store ATA holds tokens
+    /// - \[writeable] Lamports destination account
+    /// - \[writeable] Token Mint account - receives transfer fees withheld in the store ATA
+    /// - [] SPL Token or Token-2022 program account, owner of the mint
+    /// - \[writeable] Store ATA of the quote mint, closed as well, only with a quote mint
+    /// - \[writeable] Quote mint account, only with a quote mint
+    /// - [] SPL Token or Token-2022 program account, owner of the quote mint, only with
+    ///   a quote mint
+    /// - [signer] Admin account
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    CloseStore,
+    /// Add lamports to the store's trading liquidity
+    /// - \[writeable, signer] Funding account
+    /// - [] Store account
+    /// - \[writeable] Store vault account
+    /// - [] System program account
+    DepositSol(Amount),
+    /// Take lamports out of the store vault, keeping it rent exempt
+    /// - [] Store account
+    /// - \[writeable] Store vault account
+    /// - \[writeable] Destination account
+    /// - [signer] Admin or treasurer account
+    /// - [signer] M multisig signer accounts, if the authority is a multisig
+    WithdrawSol(Amount),
+    /// Move tokens of the store's own, a catalog or the quote mint into the store inventory
+    /// - [] Store account
+    /// - \[writeable] Store ATA
+    /// - \[writeable] Source token account
+    /// - [signer] Source token account owner/delegate
+    /// - [] Token Mint account
+    /// - [] SPL Token or Token-2022 program account, owner of the mint
+    /// - [signer] Admin or treasurer account
+    /// - [signer] M multisig signer accounts, if the authority is a multisig
+    DepositTokens(Amount),
+    /// Move tokens of the store's own, a catalog or the quote mint out of the store inventory
+    /// - [] Store account
+    /// - \[writeable] Store ATA
+    /// - \[writeable] Destination token account
+    /// - [] Token Mint account
+    /// - [] SPL Token or Token-2022 program account, owner of the mint
+    /// - [signer] Admin or treasurer account
+    /// - [signer] M multisig signer accounts, if the authority is a multisig
+    WithdrawTokens(Amount),
+    /// List another mint in the store catalog, enabled for trading at the given prices.
+    /// The store keeps the mint in its ATA, like its own token
+    /// - \[writeable] Store account
+    /// - [] Token Mint account
+    /// - [signer] Admin account
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    AddCatalogEntry(Prices),
+    /// Remove a mint from the store catalog. Its tokens have to be withdrawn beforehand
+    /// - \[writeable] Store account
+    /// - [] Store ATA of the mint
+    /// - [] Token Mint account
+    /// - [] SPL Token or Token-2022 program account, owner of the mint
+    /// - [signer] Admin account
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    RemoveCatalogEntry(Pubkey),
+    /// Reprice a catalog mint and enable or disable trading it
+    /// - \[writeable] Store account
+    /// - [signer] Admin or pricer account
+    /// - [signer] M multisig signer accounts, if the authority is a multisig
+    UpdateCatalogEntry {
+        mint: Pubkey,
+        prices: Prices,
+        enabled: bool,
+    },
+    /// Register the key signing off-chain quotes, or stop accepting quotes
+    /// - \[writeable] Store account
+    /// - [signer] Admin account
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    SetQuoteSigner(Option<Pubkey>),
+    /// Buy the quoted amount of tokens from a client at the quoted price. The instruction
+    /// right before has to be an Ed25519 program instruction verifying the quote signer's
+    /// signature of the borsh serialized `QuoteMessage` with side `Buy`, the client and
+    /// the current store fee
+    /// - Accounts of `Buy`, with the store account writeable to record the quote nonce
+    /// - [] Instructions sysvar
+    BuyWithQuote(Quote),
+    /// Sell the quoted amount of tokens to a client at the quoted price. The instruction
+    /// right before has to be an Ed25519 program instruction verifying the quote signer's
+    /// signature of the borsh serialized `QuoteMessage` with side `Sell`, the client and
+    /// the current store fee
+    /// - Accounts of `Sell`, with the store account writeable to record the quote nonce
+    /// - [] Instructions sysvar
+    SellWithQuote(Quote),
+    /// Settle trades in a token instead of SOL, or in SOL again. Prices are then read
+    /// as base units of the quote mint per whole token, so they should be updated along.
+    /// The quote mint cannot be the store's own or a catalog mint, and trades pass a single
+    /// token program, so it has to belong to the token program of the traded mints.
+    /// Fees held by the store have to be collected beforehand
+    /// - \[writeable] Store account
+    /// - [signer] Admin account
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    SetQuoteMint(Option<Pubkey>),
+    /// Like `Buy`, paying the client in wrapped SOL instead of lamports.
+    /// Not available for stores with a quote mint
+    /// - Accounts of `Buy`
+    /// - \[writeable] Client wrapped SOL account of the token program (sol recipient)
+    BuyWithWrappedSol {
+        amount: Amount,
+        /// Fewest lamports the client accepts for the tokens
+        min_lamports: Amount,
+    },
+    /// Like `Sell`, charging the client wrapped SOL instead of lamports. The store
+    /// unwraps the payment into its vault through its native mint ATA, which it closes
+    /// again. Not available for stores with a quote mint
+    /// - Accounts of `Sell`
+    /// - \[writeable] Client wrapped SOL account of the token program (sol source)
+    /// - \[writeable] Store ATA of the native mint
+    /// - [] Native mint account of the token program
+    SellWithWrappedSol {
+        amount: Amount,
+        /// Most lamports the client agrees to pay for the tokens
+        max_lamports: Amount,
+    },
+    /// Charge a fee on the value of every trade and set the account it is paid out to.
+    /// Clients receive the value of a `Buy` less the fee and pay the value of a `Sell`
+    /// plus the fee. Fees already held by the store go to the new recipient
+    /// - \[writeable] Store account
+    /// - [signer] Admin account
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    SetFee {
+        /// At most 10000
+        fee_bps: u16,
+        fee_recipient: Pubkey,
+    },
+    /// Pay the fees held by the store out to its fee recipient, anyone may send it
+    /// - \[writeable] Store account
+    /// - \[writeable] Store vault account
+    /// - \[writeable] Fee recipient account
+    /// - \[writeable] Store ATA of the quote mint, only with a quote mint
+    /// - \[writeable] Fee recipient ATA of the quote mint, only with a quote mint
+    /// - [] Quote mint account, only with a quote mint
+    /// - [] SPL Token or Token-2022 program account, only with a quote mint
+    CollectFees,
+    /// Pay referrers of trades a share of the fee, in basis points of the fee, at most 10000
+    /// - \[writeable] Store account
+    /// - [signer] Admin account
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    SetReferralShare(u16),
+    /// Price the store's own token at its bid and ask, on a bonding curve of the tokens sold
+    /// or from the store's reserves, which `UpdatePrice` cannot change. A curve continues
+    /// from the tokens sold on earlier curves, the store only buys back tokens sold on
+    /// curves, and trades need the store account writeable
+    /// - \[writeable] Store account
+    /// - [signer] Admin account
+    /// - [signer] M multisig signer accounts, if the admin is a multisig
+    SetPricingMode(PricingMode),
 }
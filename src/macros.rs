@@ -2,7 +2,7 @@
 macro_rules! ensure {
     ($cond:expr, $err:expr $(,)?) => {
         if !$cond {
-            return spl_token::solana_program::entrypoint::ProgramResult::Err($err);
+            return ::core::result::Result::Err($err);
         }
     };
 }
@@ -3,12 +3,32 @@ use spl_token::solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey,
 };
 
-use crate::instruction::SplStoreInstruction;
+use crate::{instruction::SplStoreInstruction, store::Amount};
 
+mod store_accept_admin;
+mod store_add_catalog_entry;
 mod store_buy;
+mod store_cancel_admin_proposal;
+mod store_close;
+mod store_collect_fees;
+mod store_deposit_sol;
+mod store_deposit_tokens;
 mod store_initialize;
+mod store_initialize_multisig;
+mod store_propose_admin;
+mod store_remove_catalog_entry;
 mod store_sell;
+mod store_set_fee;
+mod store_set_paused;
+mod store_set_pricing_mode;
+mod store_set_quote_mint;
+mod store_set_quote_signer;
+mod store_set_referral_share;
+mod store_set_role;
+mod store_update_catalog_entry;
 mod store_update_price;
+mod store_withdraw_sol;
+mod store_withdraw_tokens;
 
 pub struct Processor;
 
@@ -20,18 +40,99 @@ impl Processor {
     ) -> ProgramResult {
         let instruction = SplStoreInstruction::try_from_slice(instruction_data)?;
         match instruction {
-            SplStoreInstruction::Initialize(price, add_sol) => {
-                store_initialize::process(program_id, accounts, price, add_sol)
+            SplStoreInstruction::Initialize(prices, add_sol, index) => {
+                store_initialize::process(program_id, accounts, prices, add_sol, index)
             }
-            SplStoreInstruction::Buy(token_amount) => {
-                store_buy::process(program_id, accounts, token_amount)
+            SplStoreInstruction::Buy {
+                amount,
+                min_lamports,
+            } => store_buy::process(program_id, accounts, amount, min_lamports, None, false),
+            SplStoreInstruction::UpdatePrice { bid, ask } => {
+                store_update_price::process(program_id, accounts, bid, ask)
             }
-            SplStoreInstruction::UpdatePrice(new_price) => {
-                store_update_price::process(program_id, accounts, new_price)
+            SplStoreInstruction::Sell {
+                amount,
+                max_lamports,
+            } => store_sell::process(program_id, accounts, amount, max_lamports, None, false),
+            SplStoreInstruction::InitializeMultisig(m) => {
+                store_initialize_multisig::process(program_id, accounts, m)
             }
-            SplStoreInstruction::Sell(token_amount) => {
-                store_sell::process(program_id, accounts, token_amount)
+            SplStoreInstruction::GrantRole(role, holder) => {
+                store_set_role::process(program_id, accounts, role, Some(holder))
             }
+            SplStoreInstruction::RevokeRole(role) => {
+                store_set_role::process(program_id, accounts, role, None)
+            }
+            SplStoreInstruction::SetPaused { buy, sell } => {
+                store_set_paused::process(program_id, accounts, buy, sell)
+            }
+            SplStoreInstruction::CloseStore => store_close::process(program_id, accounts),
+            SplStoreInstruction::DepositSol(amount) => {
+                store_deposit_sol::process(program_id, accounts, amount)
+            }
+            SplStoreInstruction::WithdrawSol(amount) => {
+                store_withdraw_sol::process(program_id, accounts, amount)
+            }
+            SplStoreInstruction::DepositTokens(amount) => {
+                store_deposit_tokens::process(program_id, accounts, amount)
+            }
+            SplStoreInstruction::WithdrawTokens(amount) => {
+                store_withdraw_tokens::process(program_id, accounts, amount)
+            }
+            SplStoreInstruction::ProposeAdmin(new_admin) => {
+                store_propose_admin::process(program_id, accounts, new_admin)
+            }
+            SplStoreInstruction::AcceptAdmin => store_accept_admin::process(program_id, accounts),
+            SplStoreInstruction::CancelAdminProposal => {
+                store_cancel_admin_proposal::process(program_id, accounts)
+            }
+            SplStoreInstruction::BuyWithQuote(quote) => {
+                store_buy::process(program_id, accounts, quote.amount, 0, Some(quote), false)
+            }
+            SplStoreInstruction::SellWithQuote(quote) => store_sell::process(
+                program_id,
+                accounts,
+                quote.amount,
+                Amount::MAX,
+                Some(quote),
+                false,
+            ),
+            SplStoreInstruction::BuyWithWrappedSol {
+                amount,
+                min_lamports,
+            } => store_buy::process(program_id, accounts, amount, min_lamports, None, true),
+            SplStoreInstruction::SellWithWrappedSol {
+                amount,
+                max_lamports,
+            } => store_sell::process(program_id, accounts, amount, max_lamports, None, true),
+            SplStoreInstruction::SetFee {
+                fee_bps,
+                fee_recipient,
+            } => store_set_fee::process(program_id, accounts, fee_bps, fee_recipient),
+            SplStoreInstruction::CollectFees => store_collect_fees::process(program_id, accounts),
+            SplStoreInstruction::SetReferralShare(referral_bps) => {
+                store_set_referral_share::process(program_id, accounts, referral_bps)
+            }
+            SplStoreInstruction::SetPricingMode(pricing_mode) => {
+                store_set_pricing_mode::process(program_id, accounts, pricing_mode)
+            }
+            SplStoreInstruction::SetQuoteSigner(signer) => {
+                store_set_quote_signer::process(program_id, accounts, signer)
+            }
+            SplStoreInstruction::SetQuoteMint(quote_mint) => {
+                store_set_quote_mint::process(program_id, accounts, quote_mint)
+            }
+            SplStoreInstruction::AddCatalogEntry(prices) => {
+                store_add_catalog_entry::process(program_id, accounts, prices)
+            }
+            SplStoreInstruction::RemoveCatalogEntry(mint) => {
+                store_remove_catalog_entry::process(program_id, accounts, mint)
+            }
+            SplStoreInstruction::UpdateCatalogEntry {
+                mint,
+                prices,
+                enabled,
+            } => store_update_catalog_entry::process(program_id, accounts, mint, prices, enabled),
         }
     }
 }
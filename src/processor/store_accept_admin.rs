@@ -0,0 +1,44 @@
+use spl_associated_token_account::solana_program::{entrypoint::ProgramResult, msg};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, multisig::validate_authority},
+};
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let new_admin_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        store_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
+    match store_account.pending_admin {
+        None => return Err(SplStoreError::NoPendingAdmin.into()),
+        Some(pending_admin) => ensure!(
+            pending_admin == *new_admin_account_info.key,
+            SplStoreError::AccountNotPendingAdmin.into()
+        ),
+    }
+    validate_authority(program_id, new_admin_account_info, signer_infos)?;
+
+    store_account.admin = *new_admin_account_info.key;
+    store_account.pending_admin = None;
+    store_account.pack(store_account_info)?;
+    msg!("Store admin changed to {}", store_account.admin);
+    Ok(())
+}
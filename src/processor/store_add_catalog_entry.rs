@@ -0,0 +1,60 @@
+use spl_associated_token_account::solana_program::{entrypoint::ProgramResult, msg};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use spl_token_2022::{extension::StateWithExtensions, state::Mint};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, catalog::CatalogEntry, prices::Prices},
+};
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], prices: Prices) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let token_mint_account_info = next_account_info(accounts_info_iter)?;
+    let admin_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        store_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        spl_token::check_id(token_mint_account_info.owner)
+            || spl_token_2022::check_id(token_mint_account_info.owner),
+        ProgramError::IncorrectProgramId
+    );
+    StateWithExtensions::<Mint>::unpack(&token_mint_account_info.data.borrow())?;
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
+    store_account.check_admin(program_id, admin_account_info, signer_infos)?;
+    ensure!(
+        *token_mint_account_info.key != store_account.mint,
+        SplStoreError::MintAlreadyListed.into()
+    );
+    ensure!(
+        store_account.quote_mint != Some(*token_mint_account_info.key),
+        SplStoreError::InvalidQuoteMint.into()
+    );
+    store_account.catalog.add(CatalogEntry {
+        mint: *token_mint_account_info.key,
+        prices: prices.validate()?,
+        enabled: true,
+    })?;
+    store_account.pack(store_account_info)?;
+    msg!(
+        "Listed {} at bid {}, ask {}",
+        token_mint_account_info.key,
+        prices.bid,
+        prices.ask
+    );
+    Ok(())
+}
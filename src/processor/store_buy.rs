@@ -1,32 +1,45 @@
 use spl_associated_token_account::{
-    get_associated_token_address,
+    get_associated_token_address_with_program_id,
     solana_program::{
         account_info::{next_account_info, AccountInfo},
         entrypoint::ProgramResult,
         msg,
-        native_token::LAMPORTS_PER_SOL,
         program::invoke,
+        program_error::ProgramError,
         pubkey::Pubkey,
     },
 };
-use spl_token::{
-    instruction::transfer,
-    solana_program::{program_error::ProgramError, program_pack::Pack},
-    state::Account,
-};
 
 use crate::{
     ensure,
     error::SplStoreError,
-    store::{account::StoreAccount, Amount},
-    utils::check_ata_mint,
+    store::{
+        account::StoreAccount,
+        currency::QuoteMintAccounts,
+        curve::PricingMode,
+        pricing,
+        quote::{accept_quote, Quote, QuoteMessage, QuoteSide},
+        referral, wrapped_sol, Amount,
+    },
+    utils::{
+        check_ata_mint, check_token_program, mint_decimals, move_lamports, token_amount,
+        transfer_checked,
+    },
 };
 
-pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) -> ProgramResult {
+pub fn process(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: Amount,
+    min_lamports: Amount,
+    quote: Option<Quote>,
+    wrapped_sol: bool,
+) -> ProgramResult {
     let accounts_info_iter = &mut accounts.iter();
 
     let funding_account_info = next_account_info(accounts_info_iter)?;
     let store_account_info = next_account_info(accounts_info_iter)?;
+    let vault_account_info = next_account_info(accounts_info_iter)?;
     let store_ata_info = next_account_info(accounts_info_iter)?;
     let client_account_info = next_account_info(accounts_info_iter)?;
     let client_ata_info = next_account_info(accounts_info_iter)?;
@@ -34,14 +47,73 @@ pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) ->
     let system_program_account_info = next_account_info(accounts_info_iter)?;
     let spl_token_program_account_info = next_account_info(accounts_info_iter)?;
 
-    ensure!(
-        spl_token::check_id(spl_token_program_account_info.key),
-        ProgramError::IncorrectProgramId
-    );
+    check_token_program(spl_token_program_account_info, token_mint_account_info)?;
     ensure!(
         store_account_info.owner == program_id,
         ProgramError::IncorrectProgramId
     );
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
+    ensure!(!store_account.buy_paused, SplStoreError::StorePaused.into());
+    store_account.check_address(program_id, store_account_info)?;
+    let quote_mint_accounts = QuoteMintAccounts::next(
+        &store_account,
+        store_account_info,
+        client_account_info,
+        accounts_info_iter,
+    )?;
+    let wrapped_sol_account_info = match wrapped_sol {
+        false => None,
+        true => {
+            ensure!(
+                quote_mint_accounts.is_none(),
+                SplStoreError::WrappedSolUnavailable.into()
+            );
+            let wrapped_sol_account_info = next_account_info(accounts_info_iter)?;
+            ensure!(
+                wrapped_sol_account_info.is_writable,
+                SplStoreError::AccountNotWritable.into()
+            );
+            wrapped_sol::check_wrapped_sol_account(
+                wrapped_sol_account_info,
+                spl_token_program_account_info,
+            )?;
+            Some(wrapped_sol_account_info)
+        }
+    };
+    let listed_price = store_account
+        .trading_prices(token_mint_account_info.key)?
+        .bid;
+    let price = match quote {
+        None => listed_price,
+        Some(quote) => {
+            let instructions_sysvar_info = next_account_info(accounts_info_iter)?;
+            ensure!(
+                store_account_info.is_writable,
+                SplStoreError::AccountNotWritable.into()
+            );
+            let message = QuoteMessage {
+                side: QuoteSide::Buy,
+                store: *store_account_info.key,
+                mint: *token_mint_account_info.key,
+                client: *client_account_info.key,
+                fee_bps: store_account.fee_bps,
+                quote,
+            };
+            accept_quote(&mut store_account, &message, instructions_sysvar_info)?;
+            store_account.pack(store_account_info)?;
+            msg!("Quote {} accepted", quote.nonce);
+            quote.price
+        }
+    };
+    let referrer_account_info = referral::next_referrer(
+        vault_account_info,
+        funding_account_info,
+        client_account_info,
+        accounts_info_iter,
+    )?;
+    store_account.check_vault(program_id, store_account_info, vault_account_info)?;
+
     ensure!(
         client_account_info.is_signer,
         SplStoreError::AccountNotSigner.into()
@@ -55,9 +127,8 @@ pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) ->
         SplStoreError::AccountNotWritable.into()
     );
 
-    let acc_data = Account::unpack(&client_ata_info.data.borrow())?;
     ensure!(
-        acc_data.amount >= amount,
+        token_amount(client_ata_info)? >= amount,
         SplStoreError::InsufficientFundsForTransaction.into()
     );
 
@@ -77,50 +148,152 @@ pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) ->
 
     check_ata_mint(store_ata_info, token_mint_account_info)?;
 
-    let price = StoreAccount::get_price(store_account_info)?;
-    msg!("Price: {} SOL", price);
-    let sol_amount = amount * price;
-    let sol_lamports = sol_amount * LAMPORTS_PER_SOL;
-    ensure!(
-        store_account_info.lamports() >= sol_lamports,
-        SplStoreError::InsufficientFundsForTransaction.into()
-    );
-
     ensure!(
-        get_associated_token_address(store_account_info.key, token_mint_account_info.key)
-            == *store_ata_info.key,
+        get_associated_token_address_with_program_id(
+            store_account_info.key,
+            token_mint_account_info.key,
+            spl_token_program_account_info.key,
+        ) == *store_ata_info.key,
         SplStoreError::InvalidAtaAddress.into()
     );
     ensure!(
-        get_associated_token_address(client_account_info.key, token_mint_account_info.key)
-            == *client_ata_info.key,
+        get_associated_token_address_with_program_id(
+            client_account_info.key,
+            token_mint_account_info.key,
+            spl_token_program_account_info.key,
+        ) == *client_ata_info.key,
         SplStoreError::InvalidAtaAddress.into()
     );
 
-    let transfer_ix = transfer(
-        spl_token_program_account_info.key,
-        client_ata_info.key,
-        store_ata_info.key,
-        client_account_info.key,
-        &[&client_account_info.key],
+    let store_tokens = token_amount(store_ata_info)?;
+    let transfer_ix = transfer_checked(
+        spl_token_program_account_info,
+        client_ata_info,
+        token_mint_account_info,
+        store_ata_info,
+        client_account_info,
         amount,
     )?;
     // [writable] The source account.
+    // [] The token mint.
     // [writable] The destination account.
     // [signer] The source account’s owner/delegate.
     invoke(
         &transfer_ix,
         &[
             client_ata_info.clone(),
+            token_mint_account_info.clone(),
             store_ata_info.clone(),
             client_account_info.clone(),
         ],
     )?;
-    msg!("Client ATA ==[{} tokens]==> Store ATA", amount);
+    // A transfer fee of the mint is withheld from what the store receives
+    let received = token_amount(store_ata_info)?
+        .checked_sub(store_tokens)
+        .ok_or(SplStoreError::MathOverflow)?;
+    msg!("Client ATA ==[{} tokens]==> Store ATA", received);
 
-    **store_account_info.try_borrow_mut_lamports()? -= sol_lamports;
-    **client_account_info.try_borrow_mut_lamports()? += sol_lamports;
-    msg!("Store Account ==[{} SOL]==> Client Account", sol_amount);
+    let decimals = mint_decimals(token_mint_account_info)?;
+    let pricing_mode = store_account.mint_pricing_mode(token_mint_account_info.key);
+    let value = match (quote, pricing_mode) {
+        (None, PricingMode::Curve(curve)) => {
+            msg!("Price: curve at {} tokens sold", store_account.tokens_sold);
+            curve.buy_lamports(store_account.tokens_sold, received, decimals)?
+        }
+        (None, PricingMode::ConstantProduct) => {
+            let reserve = match &quote_mint_accounts {
+                None => store_account.vault_available_lamports(vault_account_info),
+                Some(quote_mint_accounts) => quote_mint_accounts.store_reserve(&store_account)?,
+            };
+            msg!("Price: reserves of {} and {} tokens", reserve, store_tokens);
+            pricing::reserves_buy_lamports(reserve, store_tokens, received)?
+        }
+        _ => {
+            msg!("Price: {} lamports per token", price);
+            pricing::buy_lamports(received, price, decimals)?
+        }
+    };
+    // Quoted trades move along the curve as well
+    if let PricingMode::Curve(_) = pricing_mode {
+        let tokens_sold = store_account
+            .tokens_sold
+            .checked_sub(received)
+            .ok_or(SplStoreError::CurveSupplyExceeded)?;
+        store_account.set_tokens_sold(store_account_info, tokens_sold)?;
+    }
+    // The fee is kept from the payment, a fee of at most 10000 basis points never exceeds it
+    let fee = pricing::fee(value, store_account.fee_bps)?;
+    let payment = value - fee;
+    ensure!(
+        payment >= min_lamports,
+        SplStoreError::SlippageExceeded.into()
+    );
+    // The referrer's share is paid out of the fee, the store keeps the rest
+    let reward = match referrer_account_info {
+        None => 0,
+        Some(_) => pricing::referral_reward(fee, store_account.referral_bps)?,
+    };
+    store_account.collect_fee(store_account_info, fee - reward)?;
+    msg!("Fee: {}", fee);
+
+    match &quote_mint_accounts {
+        None => {
+            ensure!(
+                store_account.vault_available_lamports(vault_account_info) >= payment + reward,
+                SplStoreError::InsufficientFundsForTransaction.into()
+            );
+            match wrapped_sol_account_info {
+                None => {
+                    move_lamports(vault_account_info, client_account_info, payment)?;
+                    msg!("Store Vault ==[{} lamports]==> Client Account", payment);
+                }
+                Some(wrapped_sol_account_info) => {
+                    wrapped_sol::wrap_from_vault(
+                        vault_account_info,
+                        wrapped_sol_account_info,
+                        spl_token_program_account_info,
+                        payment,
+                    )?;
+                    msg!(
+                        "Store Vault ==[{} lamports]==> Client wSOL account",
+                        payment
+                    );
+                }
+            }
+        }
+        Some(quote_mint_accounts) => {
+            if quote_mint_accounts.client_ata.lamports() == 0 {
+                msg!("Creating client (quote recipient) ATA...");
+                StoreAccount::initialize_ata(&[
+                    funding_account_info.clone(),
+                    quote_mint_accounts.client_ata.clone(),
+                    client_account_info.clone(),
+                    quote_mint_accounts.mint.clone(),
+                    system_program_account_info.clone(),
+                    quote_mint_accounts.token_program.clone(),
+                ])?;
+            }
+            let received =
+                quote_mint_accounts.pay_client(&store_account, store_account_info, payment)?;
+            msg!("Store quote ATA ==[{} tokens]==> Client quote ATA", payment);
+            // The quote mint's transfer fee comes out of what the client receives
+            ensure!(
+                received >= min_lamports,
+                SplStoreError::SlippageExceeded.into()
+            );
+        }
+    }
+
+    if let Some(referrer_account_info) = referrer_account_info {
+        referral::pay_referrer(
+            &store_account,
+            store_account_info,
+            vault_account_info,
+            referrer_account_info,
+            quote_mint_accounts.as_ref(),
+            reward,
+        )?;
+    }
 
     Ok(())
 }
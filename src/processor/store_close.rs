@@ -0,0 +1,223 @@
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, solana_program::msg,
+};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use spl_token_2022::{
+    extension::transfer_fee::instruction::harvest_withheld_tokens_to_mint,
+    instruction::close_account,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::account::StoreAccount,
+    utils::{
+        check_ata_mint, check_token_program, move_lamports, token_amount, transfer_checked,
+        withheld_amount,
+    },
+};
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("Store closing");
+    let accounts_info_iter = &mut accounts.iter();
+
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let vault_account_info = next_account_info(accounts_info_iter)?;
+    let store_ata_info = next_account_info(accounts_info_iter)?;
+    let token_destination_info = next_account_info(accounts_info_iter)?;
+    let lamports_destination_info = next_account_info(accounts_info_iter)?;
+    let token_mint_account_info = next_account_info(accounts_info_iter)?;
+    let spl_token_program_account_info = next_account_info(accounts_info_iter)?;
+
+    check_token_program(spl_token_program_account_info, token_mint_account_info)?;
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    let store_account = StoreAccount::unpack(store_account_info)?;
+    let quote_mint_infos = match store_account.quote_mint {
+        Some(_) => Some((
+            next_account_info(accounts_info_iter)?,
+            next_account_info(accounts_info_iter)?,
+            next_account_info(accounts_info_iter)?,
+        )),
+        None => None,
+    };
+    let admin_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        store_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        lamports_destination_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        vault_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        lamports_destination_info.key != store_account_info.key
+            && lamports_destination_info.key != vault_account_info.key,
+        ProgramError::InvalidArgument
+    );
+
+    store_account.check_admin(program_id, admin_account_info, signer_infos)?;
+    store_account.check_address(program_id, store_account_info)?;
+    store_account.check_mint(token_mint_account_info.key)?;
+    store_account.check_vault(program_id, store_account_info, vault_account_info)?;
+    ensure!(
+        store_account.fees_collected == 0,
+        SplStoreError::UnclaimedFees.into()
+    );
+    ensure!(
+        store_account.catalog.entries.is_empty(),
+        SplStoreError::CatalogNotEmpty.into()
+    );
+    if let (
+        Some(quote_mint),
+        Some((store_quote_ata_info, quote_mint_info, quote_token_program_info)),
+    ) = (store_account.quote_mint, quote_mint_infos)
+    {
+        ensure!(
+            *quote_mint_info.key == quote_mint,
+            SplStoreError::QuoteMintMismatch.into()
+        );
+        check_token_program(quote_token_program_info, quote_mint_info)?;
+        ensure!(
+            get_associated_token_address_with_program_id(
+                store_account_info.key,
+                quote_mint_info.key,
+                quote_token_program_info.key,
+            ) == *store_quote_ata_info.key,
+            SplStoreError::InvalidAtaAddress.into()
+        );
+        if store_quote_ata_info.lamports() != 0 {
+            ensure!(
+                token_amount(store_quote_ata_info)? == 0,
+                SplStoreError::QuoteMintNotWithdrawn.into()
+            );
+            close_token_account(
+                &store_account,
+                store_account_info,
+                store_quote_ata_info,
+                quote_mint_info,
+                lamports_destination_info,
+                quote_token_program_info,
+            )?;
+            msg!("Store quote ATA closed");
+        }
+    }
+    ensure!(
+        get_associated_token_address_with_program_id(
+            store_account_info.key,
+            token_mint_account_info.key,
+            spl_token_program_account_info.key,
+        ) == *store_ata_info.key,
+        SplStoreError::InvalidAtaAddress.into()
+    );
+
+    if store_ata_info.lamports() != 0 {
+        check_ata_mint(store_ata_info, token_mint_account_info)?;
+        let amount = token_amount(store_ata_info)?;
+        if amount > 0 {
+            check_ata_mint(token_destination_info, token_mint_account_info)?;
+            let transfer_ix = transfer_checked(
+                spl_token_program_account_info,
+                store_ata_info,
+                token_mint_account_info,
+                token_destination_info,
+                store_account_info,
+                amount,
+            )?;
+            // [writable] The source account.
+            // [] The token mint.
+            // [writable] The destination account.
+            // [signer] The source account’s owner/delegate.
+            store_account.invoke_signed(
+                &transfer_ix,
+                &[
+                    store_ata_info.clone(),
+                    token_mint_account_info.clone(),
+                    token_destination_info.clone(),
+                    store_account_info.clone(),
+                ],
+            )?;
+            msg!("Store ATA ==[{} tokens]==> Destination", amount);
+        }
+
+        close_token_account(
+            &store_account,
+            store_account_info,
+            store_ata_info,
+            token_mint_account_info,
+            lamports_destination_info,
+            spl_token_program_account_info,
+        )?;
+        msg!("Store ATA closed");
+    }
+
+    let lamports = vault_account_info.lamports();
+    move_lamports(vault_account_info, lamports_destination_info, lamports)?;
+    msg!("Store Vault ==[{} lamports]==> Destination", lamports);
+
+    let lamports = store_account_info.lamports();
+    move_lamports(store_account_info, lamports_destination_info, lamports)?;
+    store_account_info.data.borrow_mut().fill(0);
+    msg!("Store Account ==[{} lamports]==> Destination", lamports);
+
+    Ok(())
+}
+
+/// Close a token account of the store, sending its rent to `lamports_destination_info`.
+/// Transfer fees withheld in the account are harvested to the mint first, the token program
+/// refuses to close it otherwise
+fn close_token_account<'a>(
+    store_account: &StoreAccount,
+    store_account_info: &AccountInfo<'a>,
+    token_account_info: &AccountInfo<'a>,
+    token_mint_account_info: &AccountInfo<'a>,
+    lamports_destination_info: &AccountInfo<'a>,
+    spl_token_program_account_info: &AccountInfo<'a>,
+) -> ProgramResult {
+    if withheld_amount(token_account_info)? > 0 {
+        let harvest_ix = harvest_withheld_tokens_to_mint(
+            spl_token_program_account_info.key,
+            token_mint_account_info.key,
+            &[token_account_info.key],
+        )?;
+        // [writable] The mint.
+        // [writable] The source accounts to harvest from.
+        store_account.invoke_signed(
+            &harvest_ix,
+            &[token_mint_account_info.clone(), token_account_info.clone()],
+        )?;
+        msg!("Withheld transfer fees harvested to the mint");
+    }
+
+    let close_ix = close_account(
+        spl_token_program_account_info.key,
+        token_account_info.key,
+        lamports_destination_info.key,
+        store_account_info.key,
+        &[store_account_info.key],
+    )?;
+    // [writable] The account to close.
+    // [writable] The destination account.
+    // [signer] The account's owner.
+    store_account.invoke_signed(
+        &close_ix,
+        &[
+            token_account_info.clone(),
+            lamports_destination_info.clone(),
+            store_account_info.clone(),
+        ],
+    )
+}
@@ -0,0 +1,107 @@
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, solana_program::msg,
+};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::account::StoreAccount,
+    utils::{check_token_program, move_lamports, transfer_checked},
+};
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let vault_account_info = next_account_info(accounts_info_iter)?;
+    let fee_recipient_account_info = next_account_info(accounts_info_iter)?;
+
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        store_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
+    store_account.check_address(program_id, store_account_info)?;
+    store_account.check_vault(program_id, store_account_info, vault_account_info)?;
+    ensure!(
+        *fee_recipient_account_info.key == store_account.fee_recipient,
+        SplStoreError::FeeRecipientMismatch.into()
+    );
+
+    let fees = store_account.fees_collected;
+    match store_account.quote_mint {
+        None => {
+            ensure!(
+                vault_account_info.is_writable && fee_recipient_account_info.is_writable,
+                SplStoreError::AccountNotWritable.into()
+            );
+            move_lamports(vault_account_info, fee_recipient_account_info, fees)?;
+            msg!("Store Vault ==[{} lamports]==> Fee recipient", fees);
+        }
+        Some(quote_mint) => {
+            let store_ata_info = next_account_info(accounts_info_iter)?;
+            let fee_recipient_ata_info = next_account_info(accounts_info_iter)?;
+            let quote_mint_account_info = next_account_info(accounts_info_iter)?;
+            let spl_token_program_account_info = next_account_info(accounts_info_iter)?;
+
+            ensure!(
+                *quote_mint_account_info.key == quote_mint,
+                SplStoreError::QuoteMintMismatch.into()
+            );
+            check_token_program(spl_token_program_account_info, quote_mint_account_info)?;
+            ensure!(
+                get_associated_token_address_with_program_id(
+                    store_account_info.key,
+                    &quote_mint,
+                    spl_token_program_account_info.key,
+                ) == *store_ata_info.key,
+                SplStoreError::InvalidAtaAddress.into()
+            );
+            ensure!(
+                get_associated_token_address_with_program_id(
+                    fee_recipient_account_info.key,
+                    &quote_mint,
+                    spl_token_program_account_info.key,
+                ) == *fee_recipient_ata_info.key,
+                SplStoreError::InvalidAtaAddress.into()
+            );
+
+            let transfer_ix = transfer_checked(
+                spl_token_program_account_info,
+                store_ata_info,
+                quote_mint_account_info,
+                fee_recipient_ata_info,
+                store_account_info,
+                fees,
+            )?;
+            // [writable] The source account.
+            // [] The token mint.
+            // [writable] The destination account.
+            // [signer] The source account’s owner/delegate.
+            store_account.invoke_signed(
+                &transfer_ix,
+                &[
+                    store_ata_info.clone(),
+                    quote_mint_account_info.clone(),
+                    fee_recipient_ata_info.clone(),
+                    store_account_info.clone(),
+                ],
+            )?;
+            msg!("Store quote ATA ==[{} tokens]==> Fee recipient ATA", fees);
+        }
+    }
+
+    store_account.fees_collected = 0;
+    store_account.pack(store_account_info)
+}
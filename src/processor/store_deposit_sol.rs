@@ -0,0 +1,58 @@
+use spl_associated_token_account::solana_program::{msg, program::invoke};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction, system_program,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, Amount},
+};
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+
+    let funding_account_info = next_account_info(accounts_info_iter)?;
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let vault_account_info = next_account_info(accounts_info_iter)?;
+    let system_program_account_info = next_account_info(accounts_info_iter)?;
+
+    ensure!(
+        system_program::check_id(system_program_account_info.key),
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        funding_account_info.is_signer,
+        SplStoreError::AccountNotSigner.into()
+    );
+    ensure!(
+        vault_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    let store_account = StoreAccount::unpack(store_account_info)?;
+    store_account.check_vault(program_id, store_account_info, vault_account_info)?;
+
+    let transfer_ix =
+        system_instruction::transfer(funding_account_info.key, vault_account_info.key, amount);
+    // [WRITE, SIGNER] Funding account
+    // [WRITE] Recipient account
+    invoke(
+        &transfer_ix,
+        &[
+            funding_account_info.clone(),
+            vault_account_info.clone(),
+            system_program_account_info.clone(),
+        ],
+    )?;
+    msg!("Funding Account ==[{} lamports]==> Store Vault", amount);
+
+    Ok(())
+}
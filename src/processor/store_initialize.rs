@@ -8,14 +8,21 @@ use spl_token::solana_program::{
 
 use crate::{
     ensure,
-    store::{account::StoreAccount, Amount, Price},
+    error::SplStoreError,
+    store::{
+        account::{StoreAccount, STORE_SEED_PREFIX, VAULT_SEED_PREFIX},
+        multisig::validate_authority,
+        prices::Prices,
+        Amount,
+    },
 };
 
 pub fn process(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    price: Price,
+    prices: Prices,
     add_sol: Amount,
+    index: u64,
 ) -> ProgramResult {
     msg!("Store initialization");
     let accounts_info_iter = &mut accounts.iter();
@@ -27,12 +34,43 @@ pub fn process(
     let system_program_account_info = next_account_info(accounts_info_iter)?;
     let spl_token_program_account_info = next_account_info(accounts_info_iter)?;
     let admin_account_info = next_account_info(accounts_info_iter)?;
+    let vault_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
 
-    if store_account_info.lamports() == 0 {
+    validate_authority(program_id, admin_account_info, signer_infos)?;
+
+    let (store_address, bump) = StoreAccount::find_address(
+        program_id,
+        admin_account_info.key,
+        token_mint_account_info.key,
+        index,
+    );
+    ensure!(
+        store_address == *store_account_info.key,
+        SplStoreError::InvalidStoreAddress.into()
+    );
+    let (vault_address, vault_bump) =
+        StoreAccount::find_vault_address(program_id, store_account_info.key);
+    ensure!(
+        vault_address == *vault_account_info.key,
+        SplStoreError::InvalidVaultAddress.into()
+    );
+
+    if store_account_info.owner != program_id {
         StoreAccount::initialize_account(
             program_id,
-            &[funding_account_info.clone(), store_account_info.clone()],
-            add_sol,
+            &[
+                funding_account_info.clone(),
+                store_account_info.clone(),
+                system_program_account_info.clone(),
+            ],
+            &[
+                STORE_SEED_PREFIX,
+                admin_account_info.key.as_ref(),
+                token_mint_account_info.key.as_ref(),
+                &index.to_le_bytes(),
+                &[bump],
+            ],
         )?;
     }
 
@@ -41,17 +79,44 @@ pub fn process(
         ProgramError::IncorrectProgramId
     );
 
-    let mut store_account: StoreAccount =
-        borsh::BorshDeserialize::try_from_slice(&store_account_info.data.borrow())?;
+    let mut store_account = StoreAccount::unpack_unchecked(store_account_info)?;
+    ensure!(
+        !store_account.is_initialized,
+        SplStoreError::StoreAlreadyInitialized.into()
+    );
 
-    store_account.admin = admin_account_info.key.clone();
-    store_account.price = price;
+    if vault_account_info.owner != program_id {
+        StoreAccount::initialize_vault(
+            program_id,
+            &[
+                funding_account_info.clone(),
+                vault_account_info.clone(),
+                system_program_account_info.clone(),
+            ],
+            add_sol,
+            &[
+                VAULT_SEED_PREFIX,
+                store_account_info.key.as_ref(),
+                &[vault_bump],
+            ],
+        )?;
+    }
 
-    borsh::BorshSerialize::serialize(
-        &store_account,
-        &mut &mut store_account_info.data.borrow_mut()[..],
-    )?;
-    msg!("Token initial price set to {}", price);
+    store_account.is_initialized = true;
+    store_account.admin = *admin_account_info.key;
+    store_account.prices = prices.validate()?;
+    store_account.pending_admin = None;
+    store_account.creator = *admin_account_info.key;
+    store_account.bump = bump;
+    store_account.vault_bump = vault_bump;
+    store_account.mint = *token_mint_account_info.key;
+    store_account.index = index;
+    store_account.pack(store_account_info)?;
+    msg!(
+        "Token initial prices set to bid {}, ask {}",
+        prices.bid,
+        prices.ask
+    );
 
     if store_ata_info.lamports() == 0 {
         msg!("Initializing store ATA...");
@@ -0,0 +1,97 @@
+use spl_associated_token_account::solana_program::{msg, program::invoke};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::create_account,
+    system_program,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::multisig::{Multisig, MAX_SIGNERS},
+};
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], m: u8) -> ProgramResult {
+    msg!("Multisig initialization");
+    let accounts_info_iter = &mut accounts.iter();
+
+    let funding_account_info = next_account_info(accounts_info_iter)?;
+    let multisig_account_info = next_account_info(accounts_info_iter)?;
+    let system_program_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        system_program::check_id(system_program_account_info.key),
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        funding_account_info.is_signer,
+        SplStoreError::AccountNotSigner.into()
+    );
+    ensure!(
+        funding_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        multisig_account_info.is_signer,
+        SplStoreError::AccountNotSigner.into()
+    );
+    ensure!(
+        multisig_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        multisig_account_info.lamports() == 0,
+        ProgramError::AccountAlreadyInitialized
+    );
+    ensure!(
+        !signer_infos.is_empty() && signer_infos.len() <= MAX_SIGNERS,
+        SplStoreError::InvalidMultisigConfig.into()
+    );
+    ensure!(
+        m >= 1 && m as usize <= signer_infos.len(),
+        SplStoreError::InvalidMultisigConfig.into()
+    );
+    let has_duplicates = signer_infos
+        .iter()
+        .enumerate()
+        .any(|(position, signer_info)| {
+            signer_infos[..position]
+                .iter()
+                .any(|other| other.key == signer_info.key)
+        });
+    ensure!(!has_duplicates, SplStoreError::InvalidMultisigConfig.into());
+
+    let space = Multisig::LEN;
+    let lamports = Rent::default().minimum_balance(space);
+    let create_account_ix = create_account(
+        funding_account_info.key,
+        multisig_account_info.key,
+        lamports,
+        space as u64,
+        program_id,
+    );
+    // [WRITE, SIGNER] Funding account
+    // [WRITE, SIGNER] New account
+    invoke(
+        &create_account_ix,
+        &[funding_account_info.clone(), multisig_account_info.clone()],
+    )?;
+
+    let mut multisig = Multisig {
+        is_initialized: true,
+        m,
+        n: signer_infos.len() as u8,
+        ..Default::default()
+    };
+    for (key, signer_info) in multisig.signers.iter_mut().zip(signer_infos) {
+        *key = *signer_info.key;
+    }
+    multisig.pack(multisig_account_info)?;
+    msg!("Multisig {} of {} initialized", m, multisig.n);
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    solana_program::{entrypoint::ProgramResult, msg},
+};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::account::StoreAccount,
+    utils::{check_token_program, token_amount},
+};
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], mint: Pubkey) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let store_ata_info = next_account_info(accounts_info_iter)?;
+    let token_mint_account_info = next_account_info(accounts_info_iter)?;
+    let spl_token_program_account_info = next_account_info(accounts_info_iter)?;
+    let admin_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        store_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        *token_mint_account_info.key == mint,
+        SplStoreError::WrongAccountMint.into()
+    );
+    check_token_program(spl_token_program_account_info, token_mint_account_info)?;
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
+    store_account.check_admin(program_id, admin_account_info, signer_infos)?;
+    // Delisting a mint the store still holds would strand its inventory
+    ensure!(
+        get_associated_token_address_with_program_id(
+            store_account_info.key,
+            &mint,
+            spl_token_program_account_info.key,
+        ) == *store_ata_info.key,
+        SplStoreError::InvalidAtaAddress.into()
+    );
+    ensure!(
+        store_ata_info.lamports() == 0 || token_amount(store_ata_info)? == 0,
+        SplStoreError::CatalogMintNotWithdrawn.into()
+    );
+    store_account.catalog.remove(&mint)?;
+    store_account.pack(store_account_info)?;
+    msg!("Delisted {}", mint);
+    Ok(())
+}
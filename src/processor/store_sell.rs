@@ -1,30 +1,49 @@
 use spl_associated_token_account::{
-    get_associated_token_address,
+    get_associated_token_address_with_program_id,
     instruction::create_associated_token_account,
     solana_program::{
         account_info::{next_account_info, AccountInfo},
         entrypoint::ProgramResult,
         msg,
-        native_token::LAMPORTS_PER_SOL,
         program::invoke,
         program_error::ProgramError,
         pubkey::Pubkey,
+        system_instruction, system_program,
     },
 };
-use spl_token::{instruction::transfer, solana_program::program_pack::Pack, state::Account};
 
 use crate::{
     ensure,
     error::SplStoreError,
-    store::{account::StoreAccount, Amount},
-    utils::check_ata_mint,
+    store::{
+        account::StoreAccount,
+        currency::QuoteMintAccounts,
+        curve::PricingMode,
+        pricing,
+        quote::{accept_quote, Quote, QuoteMessage, QuoteSide},
+        referral,
+        wrapped_sol::WrappedSolPayment,
+        Amount,
+    },
+    utils::{
+        check_ata_mint, check_token_program, mint_decimals, move_lamports, token_amount,
+        transfer_checked,
+    },
 };
 
-pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) -> ProgramResult {
+pub fn process(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: Amount,
+    max_lamports: Amount,
+    quote: Option<Quote>,
+    wrapped_sol: bool,
+) -> ProgramResult {
     let accounts_info_iter = &mut accounts.iter();
 
     let funding_account_info = next_account_info(accounts_info_iter)?;
     let store_account_info = next_account_info(accounts_info_iter)?;
+    let vault_account_info = next_account_info(accounts_info_iter)?;
     let store_ata_info = next_account_info(accounts_info_iter)?;
     let client_account_info = next_account_info(accounts_info_iter)?;
     let client_ata_info = next_account_info(accounts_info_iter)?;
@@ -32,20 +51,85 @@ pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) ->
     let system_program_account_info = next_account_info(accounts_info_iter)?;
     let spl_token_program_account_info = next_account_info(accounts_info_iter)?;
 
+    check_token_program(spl_token_program_account_info, token_mint_account_info)?;
     ensure!(
-        spl_token::check_id(&spl_token_program_account_info.key),
+        store_account_info.owner == program_id,
         ProgramError::IncorrectProgramId
     );
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
     ensure!(
-        store_account_info.owner == program_id,
+        !store_account.sell_paused,
+        SplStoreError::StorePaused.into()
+    );
+    store_account.check_address(program_id, store_account_info)?;
+    let quote_mint_accounts = QuoteMintAccounts::next(
+        &store_account,
+        store_account_info,
+        client_account_info,
+        accounts_info_iter,
+    )?;
+    let wrapped_sol_payment = match wrapped_sol {
+        false => None,
+        true => {
+            ensure!(
+                quote_mint_accounts.is_none(),
+                SplStoreError::WrappedSolUnavailable.into()
+            );
+            Some(WrappedSolPayment::next(
+                store_account_info,
+                spl_token_program_account_info,
+                accounts_info_iter,
+            )?)
+        }
+    };
+    let listed_price = store_account
+        .trading_prices(token_mint_account_info.key)?
+        .ask;
+    let price = match quote {
+        None => listed_price,
+        Some(quote) => {
+            let instructions_sysvar_info = next_account_info(accounts_info_iter)?;
+            ensure!(
+                store_account_info.is_writable,
+                SplStoreError::AccountNotWritable.into()
+            );
+            let message = QuoteMessage {
+                side: QuoteSide::Sell,
+                store: *store_account_info.key,
+                mint: *token_mint_account_info.key,
+                client: *client_account_info.key,
+                fee_bps: store_account.fee_bps,
+                quote,
+            };
+            accept_quote(&mut store_account, &message, instructions_sysvar_info)?;
+            store_account.pack(store_account_info)?;
+            msg!("Quote {} accepted", quote.nonce);
+            quote.price
+        }
+    };
+    let referrer_account_info = referral::next_referrer(
+        vault_account_info,
+        funding_account_info,
+        client_account_info,
+        accounts_info_iter,
+    )?;
+    store_account.check_vault(program_id, store_account_info, vault_account_info)?;
+
+    ensure!(
+        vault_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        system_program::check_id(system_program_account_info.key),
         ProgramError::IncorrectProgramId
     );
     ensure!(
-        store_account_info.is_signer,
+        client_account_info.is_signer,
         SplStoreError::AccountNotSigner.into()
     );
     ensure!(
-        store_account_info.is_writable,
+        client_account_info.is_writable,
         SplStoreError::AccountNotWritable.into()
     );
     ensure!(
@@ -65,9 +149,8 @@ pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) ->
         SplStoreError::AccountNotWritable.into()
     );
 
-    let acc_data = Account::unpack(&store_ata_info.data.borrow())?;
     ensure!(
-        acc_data.amount >= amount,
+        token_amount(store_ata_info)? >= amount,
         SplStoreError::InsufficientFundsForTransaction.into()
     );
 
@@ -102,50 +185,176 @@ pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) ->
 
     check_ata_mint(client_ata_info, token_mint_account_info)?;
 
-    let price = StoreAccount::get_price(store_account_info)?;
-    msg!("Price: {} SOL", price);
-    let sol_amount = amount * price;
-    let sol_lamports = sol_amount * LAMPORTS_PER_SOL;
+    let decimals = mint_decimals(token_mint_account_info)?;
+    let pricing_mode = store_account.mint_pricing_mode(token_mint_account_info.key);
+    let value = match (quote, pricing_mode) {
+        (None, PricingMode::Curve(curve)) => {
+            msg!("Price: curve at {} tokens sold", store_account.tokens_sold);
+            curve.sell_lamports(store_account.tokens_sold, amount, decimals)?
+        }
+        (None, PricingMode::ConstantProduct) => {
+            let reserve = match &quote_mint_accounts {
+                None => store_account.vault_available_lamports(vault_account_info),
+                Some(quote_mint_accounts) => quote_mint_accounts.store_reserve(&store_account)?,
+            };
+            let store_tokens = token_amount(store_ata_info)?;
+            msg!("Price: reserves of {} and {} tokens", reserve, store_tokens);
+            pricing::reserves_sell_lamports(reserve, store_tokens, amount)?
+        }
+        _ => {
+            msg!("Price: {} lamports per token", price);
+            pricing::sell_lamports(amount, price, decimals)?
+        }
+    };
+    // Quoted trades move along the curve as well
+    if let PricingMode::Curve(_) = pricing_mode {
+        let tokens_sold = store_account
+            .tokens_sold
+            .checked_add(amount)
+            .ok_or(SplStoreError::MathOverflow)?;
+        store_account.set_tokens_sold(store_account_info, tokens_sold)?;
+    }
+    let fee = pricing::fee(value, store_account.fee_bps)?;
+    let payment = value.checked_add(fee).ok_or(SplStoreError::MathOverflow)?;
     ensure!(
-        client_account_info.lamports() >= sol_lamports,
-        SplStoreError::InsufficientFundsForTransaction.into()
+        payment <= max_lamports,
+        SplStoreError::SlippageExceeded.into()
     );
+    // The referrer's share is paid out of the fee, the store keeps the rest
+    let reward = match referrer_account_info {
+        None => 0,
+        Some(_) => pricing::referral_reward(fee, store_account.referral_bps)?,
+    };
+    store_account.collect_fee(store_account_info, fee - reward)?;
+    msg!("Fee: {}", fee);
 
     ensure!(
-        get_associated_token_address(&store_account_info.key, &token_mint_account_info.key)
-            == *store_ata_info.key,
+        get_associated_token_address_with_program_id(
+            store_account_info.key,
+            token_mint_account_info.key,
+            spl_token_program_account_info.key,
+        ) == *store_ata_info.key,
         SplStoreError::InvalidAtaAddress.into()
     );
     ensure!(
-        get_associated_token_address(&client_account_info.key, &token_mint_account_info.key)
-            == *client_ata_info.key,
+        get_associated_token_address_with_program_id(
+            client_account_info.key,
+            token_mint_account_info.key,
+            spl_token_program_account_info.key,
+        ) == *client_ata_info.key,
         SplStoreError::InvalidAtaAddress.into()
     );
 
-    let transfer_ix = transfer(
-        &spl_token_program_account_info.key,
-        &store_ata_info.key,
-        &client_ata_info.key,
-        &store_account_info.key,
-        &[&store_account_info.key],
+    // The client pays for the tokens leaving the store, a transfer fee of the mint
+    // is withheld from what it receives
+    let transfer_ix = transfer_checked(
+        spl_token_program_account_info,
+        store_ata_info,
+        token_mint_account_info,
+        client_ata_info,
+        store_account_info,
         amount,
     )?;
     // [writable] The source account.
+    // [] The token mint.
     // [writable] The destination account.
     // [signer] The source account’s owner/delegate.
-    invoke(
+    store_account.invoke_signed(
         &transfer_ix,
         &[
             store_ata_info.clone(),
+            token_mint_account_info.clone(),
             client_ata_info.clone(),
             store_account_info.clone(),
         ],
     )?;
     msg!("Store ATA ==[{} tokens]==> Client ATA", amount);
 
-    **client_account_info.try_borrow_mut_lamports()? -= sol_lamports;
-    **store_account_info.try_borrow_mut_lamports()? += sol_lamports;
-    msg!("Client Account ==[{} SOL]==> Store Account", sol_amount);
+    match (&quote_mint_accounts, wrapped_sol_payment) {
+        (None, None) => {
+            ensure!(
+                client_account_info.lamports() >= payment,
+                SplStoreError::InsufficientFundsForTransaction.into()
+            );
+            let payment_ix = system_instruction::transfer(
+                client_account_info.key,
+                vault_account_info.key,
+                payment,
+            );
+            // [WRITE, SIGNER] Funding account
+            // [WRITE] Recipient account
+            invoke(
+                &payment_ix,
+                &[
+                    client_account_info.clone(),
+                    vault_account_info.clone(),
+                    system_program_account_info.clone(),
+                ],
+            )?;
+            msg!("Client Account ==[{} lamports]==> Store Vault", payment);
+        }
+        (None, Some(wrapped_sol_payment)) => {
+            let created = wrapped_sol_payment.store_ata.lamports() == 0;
+            if created {
+                msg!("Creating store (wSOL recipient) ATA...");
+                StoreAccount::initialize_ata(&[
+                    funding_account_info.clone(),
+                    wrapped_sol_payment.store_ata.clone(),
+                    store_account_info.clone(),
+                    wrapped_sol_payment.mint.clone(),
+                    system_program_account_info.clone(),
+                    spl_token_program_account_info.clone(),
+                ])?;
+            }
+            let ata_lamports = wrapped_sol_payment.store_ata.lamports();
+            wrapped_sol_payment.charge_client(
+                &store_account,
+                store_account_info,
+                client_account_info,
+                vault_account_info,
+                spl_token_program_account_info,
+                payment,
+            )?;
+            // Rent of the ATA goes back to the funding account that just paid it
+            if created {
+                move_lamports(vault_account_info, funding_account_info, ata_lamports)?;
+            }
+            msg!(
+                "Client wSOL account ==[{} lamports]==> Store Vault",
+                payment
+            );
+        }
+        (Some(quote_mint_accounts), _) => {
+            if quote_mint_accounts.store_ata.lamports() == 0 {
+                msg!("Creating store (quote recipient) ATA...");
+                StoreAccount::initialize_ata(&[
+                    funding_account_info.clone(),
+                    quote_mint_accounts.store_ata.clone(),
+                    store_account_info.clone(),
+                    quote_mint_accounts.mint.clone(),
+                    system_program_account_info.clone(),
+                    quote_mint_accounts.token_program.clone(),
+                ])?;
+            }
+            let charged = quote_mint_accounts.charge_client(client_account_info, payment)?;
+            ensure!(
+                charged <= max_lamports,
+                SplStoreError::SlippageExceeded.into()
+            );
+            msg!("Client quote ATA ==[{} tokens]==> Store quote ATA", charged);
+        }
+    }
+
+    if let Some(referrer_account_info) = referrer_account_info {
+        referral::pay_referrer(
+            &store_account,
+            store_account_info,
+            vault_account_info,
+            referrer_account_info,
+            quote_mint_accounts.as_ref(),
+            reward,
+        )?;
+    }
 
     Ok(())
 }
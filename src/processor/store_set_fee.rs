@@ -0,0 +1,42 @@
+use spl_associated_token_account::solana_program::{entrypoint::ProgramResult, msg};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, pricing::BPS_DENOMINATOR},
+};
+
+pub fn process(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fee_bps: u16,
+    fee_recipient: Pubkey,
+) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let admin_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        store_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(fee_bps <= BPS_DENOMINATOR, SplStoreError::InvalidFee.into());
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
+    store_account.check_admin(program_id, admin_account_info, signer_infos)?;
+    store_account.fee_bps = fee_bps;
+    store_account.fee_recipient = fee_recipient;
+    store_account.pack(store_account_info)?;
+    msg!("Fee set to {} bps, paid out to {}", fee_bps, fee_recipient);
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use spl_associated_token_account::solana_program::{entrypoint::ProgramResult, msg};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{ensure, error::SplStoreError, store::account::StoreAccount};
+
+pub fn process(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    quote_mint: Option<Pubkey>,
+) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let admin_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        store_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
+    store_account.check_admin(program_id, admin_account_info, signer_infos)?;
+    // Fees are held in the currency trades settle in
+    ensure!(
+        store_account.fees_collected == 0,
+        SplStoreError::UnclaimedFees.into()
+    );
+    if let Some(quote_mint) = quote_mint {
+        ensure!(
+            quote_mint != store_account.mint && store_account.catalog.entry(&quote_mint).is_none(),
+            SplStoreError::InvalidQuoteMint.into()
+        );
+    }
+    store_account.quote_mint = quote_mint;
+    store_account.pack(store_account_info)?;
+    match quote_mint {
+        Some(quote_mint) => msg!("Trades settle in {}", quote_mint),
+        None => msg!("Trades settle in SOL"),
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use spl_associated_token_account::solana_program::{entrypoint::ProgramResult, msg};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{ensure, error::SplStoreError, store::account::StoreAccount};
+
+pub fn process(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    signer: Option<Pubkey>,
+) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let admin_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        store_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
+    store_account.check_admin(program_id, admin_account_info, signer_infos)?;
+    store_account.quote_signer = signer;
+    store_account.pack(store_account_info)?;
+    match signer {
+        Some(signer) => msg!("Quote signer set to {}", signer),
+        None => msg!("Quote signer removed"),
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use spl_associated_token_account::solana_program::{entrypoint::ProgramResult, msg};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, role::Role},
+};
+
+/// Grant `role` to `holder`, or revoke it when `holder` is `None`
+pub fn process(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    role: Role,
+    holder: Option<Pubkey>,
+) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let authority_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        store_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
+    if role == Role::RoleManager {
+        store_account.check_admin(program_id, authority_account_info, signer_infos)?;
+    } else {
+        store_account.check_role(
+            program_id,
+            Role::RoleManager,
+            authority_account_info,
+            signer_infos,
+        )?;
+    }
+    store_account.roles.set_holder(role, holder);
+    store_account.pack(store_account_info)?;
+    match holder {
+        Some(holder) => msg!("Granted {:?} to {}", role, holder),
+        None => msg!("Revoked {:?}", role),
+    }
+    Ok(())
+}
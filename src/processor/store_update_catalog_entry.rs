@@ -0,0 +1,57 @@
+use spl_associated_token_account::solana_program::{entrypoint::ProgramResult, msg};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, prices::Prices, role::Role},
+};
+
+pub fn process(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mint: Pubkey,
+    prices: Prices,
+    enabled: bool,
+) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let authority_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        store_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+
+    let mut store_account = StoreAccount::unpack(store_account_info)?;
+    store_account.check_role(
+        program_id,
+        Role::Pricer,
+        authority_account_info,
+        signer_infos,
+    )?;
+    let entry = store_account
+        .catalog
+        .entry_mut(&mint)
+        .ok_or(SplStoreError::MintNotListed)?;
+    entry.prices = prices.validate()?;
+    entry.enabled = enabled;
+    store_account.pack(store_account_info)?;
+    msg!(
+        "{} prices set to bid {}, ask {}, enabled: {}",
+        mint,
+        prices.bid,
+        prices.ask,
+        enabled
+    );
+    Ok(())
+}
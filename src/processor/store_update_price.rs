@@ -10,13 +10,26 @@ use crate::{
     store::{account::StoreAccount, Price},
 };
 
-pub fn process(_program_id: &Pubkey, accounts: &[AccountInfo], new_price: Price) -> ProgramResult {
+pub fn process(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    bid: Option<Price>,
+    ask: Option<Price>,
+) -> ProgramResult {
     let accounts_info_iter = &mut accounts.iter();
     let account_info = next_account_info(accounts_info_iter)?;
-    let admin_account_info = next_account_info(accounts_info_iter)?;
+    let authority_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
     ensure!(
         account_info.is_writable,
         SplStoreError::AccountNotWritable.into()
     );
-    StoreAccount::update_price(account_info, admin_account_info, new_price)
+    StoreAccount::update_price(
+        program_id,
+        account_info,
+        authority_account_info,
+        signer_infos,
+        bid,
+        ask,
+    )
 }
@@ -0,0 +1,61 @@
+use spl_associated_token_account::solana_program::msg;
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, role::Role, Amount},
+    utils::move_lamports,
+};
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let vault_account_info = next_account_info(accounts_info_iter)?;
+    let destination_account_info = next_account_info(accounts_info_iter)?;
+    let authority_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        vault_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        destination_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        destination_account_info.key != vault_account_info.key,
+        ProgramError::InvalidArgument
+    );
+
+    let store_account = StoreAccount::unpack(store_account_info)?;
+    store_account.check_vault(program_id, store_account_info, vault_account_info)?;
+    store_account.check_role(
+        program_id,
+        Role::Treasurer,
+        authority_account_info,
+        signer_infos,
+    )?;
+
+    let available = store_account.vault_available_lamports(vault_account_info);
+    ensure!(
+        amount <= available,
+        SplStoreError::WithdrawalBreaksRentExemption.into()
+    );
+
+    move_lamports(vault_account_info, destination_account_info, amount)?;
+    msg!("Store Vault ==[{} lamports]==> Destination", amount);
+
+    Ok(())
+}
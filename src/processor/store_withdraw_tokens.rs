@@ -0,0 +1,86 @@
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, solana_program::msg,
+};
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, role::Role, Amount},
+    utils::{check_ata_mint, check_token_program, token_amount, transfer_checked},
+};
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], amount: Amount) -> ProgramResult {
+    let accounts_info_iter = &mut accounts.iter();
+
+    let store_account_info = next_account_info(accounts_info_iter)?;
+    let store_ata_info = next_account_info(accounts_info_iter)?;
+    let destination_account_info = next_account_info(accounts_info_iter)?;
+    let token_mint_account_info = next_account_info(accounts_info_iter)?;
+    let spl_token_program_account_info = next_account_info(accounts_info_iter)?;
+    let authority_account_info = next_account_info(accounts_info_iter)?;
+    let signer_infos = accounts_info_iter.as_slice();
+
+    check_token_program(spl_token_program_account_info, token_mint_account_info)?;
+    ensure!(
+        store_account_info.owner == program_id,
+        ProgramError::IncorrectProgramId
+    );
+
+    let store_account = StoreAccount::unpack(store_account_info)?;
+    store_account.check_role(
+        program_id,
+        Role::Treasurer,
+        authority_account_info,
+        signer_infos,
+    )?;
+    store_account.check_address(program_id, store_account_info)?;
+    store_account.check_listed(token_mint_account_info.key)?;
+
+    ensure!(
+        get_associated_token_address_with_program_id(
+            store_account_info.key,
+            token_mint_account_info.key,
+            spl_token_program_account_info.key,
+        ) == *store_ata_info.key,
+        SplStoreError::InvalidAtaAddress.into()
+    );
+    check_ata_mint(store_ata_info, token_mint_account_info)?;
+    check_ata_mint(destination_account_info, token_mint_account_info)?;
+    if store_account.quote_mint == Some(*token_mint_account_info.key) {
+        ensure!(
+            token_amount(store_ata_info)?.saturating_sub(store_account.fees_collected) >= amount,
+            SplStoreError::UnclaimedFees.into()
+        );
+    }
+
+    let transfer_ix = transfer_checked(
+        spl_token_program_account_info,
+        store_ata_info,
+        token_mint_account_info,
+        destination_account_info,
+        store_account_info,
+        amount,
+    )?;
+    // [writable] The source account.
+    // [] The token mint.
+    // [writable] The destination account.
+    // [signer] The source account’s owner/delegate.
+    store_account.invoke_signed(
+        &transfer_ix,
+        &[
+            store_ata_info.clone(),
+            token_mint_account_info.clone(),
+            destination_account_info.clone(),
+            store_account_info.clone(),
+        ],
+    )?;
+    msg!("Store ATA ==[{} tokens]==> Destination", amount);
+
+    Ok(())
+}
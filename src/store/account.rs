@@ -1,108 +1,395 @@
-use std::mem::size_of;
-
 use borsh_derive::{BorshDeserialize, BorshSerialize};
 use spl_associated_token_account::{
-    get_associated_token_address,
+    get_associated_token_address_with_program_id,
     instruction::create_associated_token_account,
-    solana_program::{account_info::next_account_info, program::invoke},
+    solana_program::{
+        account_info::next_account_info,
+        instruction::Instruction,
+        program::{invoke, invoke_signed},
+    },
 };
 use spl_token::solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
-    pubkey::Pubkey, rent::Rent, system_instruction::create_account, system_program,
+    pubkey::Pubkey, rent::Rent, system_program,
 };
 
 use crate::{
     ensure,
     error::SplStoreError,
-    store::{Amount, Price},
+    store::{
+        catalog::Catalog,
+        curve::PricingMode,
+        multisig::{validate_authority, Multisig},
+        prices::Prices,
+        role::{Role, StoreRoles},
+        Amount, Price,
+    },
+    utils::create_pda_account,
 };
 
+/// Prefix of the seeds the store address is derived from
+pub const STORE_SEED_PREFIX: &[u8] = b"store";
+/// Prefix of the seeds the store SOL vault address is derived from
+pub const VAULT_SEED_PREFIX: &[u8] = b"vault";
+
 #[derive(Debug, Default, BorshSerialize, BorshDeserialize)]
 pub struct StoreAccount {
-    pub price: Price,
+    pub is_initialized: bool,
+    pub prices: Prices,
     pub admin: Pubkey,
+    /// Admin nominated by the current admin, takes over once it accepts
+    pub pending_admin: Option<Pubkey>,
+    pub roles: StoreRoles,
+    /// Whether the store refuses to buy tokens from clients
+    pub buy_paused: bool,
+    /// Whether the store refuses to sell tokens to clients
+    pub sell_paused: bool,
+    /// Admin at initialization, part of the store address seeds
+    pub creator: Pubkey,
+    /// Bump seed of the store address
+    pub bump: u8,
+    /// Bump seed of the SOL vault address
+    pub vault_bump: u8,
+    /// Token the store trades
+    pub mint: Pubkey,
+    /// Distinguishes stores of the same creator and mint, part of the store address seeds
+    pub index: u64,
+    /// Mints traded next to the store's own token
+    pub catalog: Catalog,
+    /// Ed25519 key signing off-chain quotes, quoted trades are refused without one
+    pub quote_signer: Option<Pubkey>,
+    /// Greatest nonce of an accepted quote
+    pub last_quote_nonce: u64,
+    /// Token trades settle in instead of SOL, kept in the ATA of the store
+    pub quote_mint: Option<Pubkey>,
+    /// Fee charged on the value of every trade, in basis points
+    pub fee_bps: u16,
+    /// Account the collected fees are paid out to
+    pub fee_recipient: Pubkey,
+    /// Fees held by the store until they are paid out, in lamports
+    /// or base units of the quote mint
+    pub fees_collected: u64,
+    /// Share of the fee of a trade paid to the referrer of the trade, in basis points
+    pub referral_bps: u16,
+    /// How the store prices its own token
+    pub pricing_mode: PricingMode,
+    /// Base units of the store's own token sold on the bonding curve, less those bought back
+    pub tokens_sold: Amount,
+    /// Nonces of accepted quotes below `last_quote_nonce`, bit `i` standing for the nonce
+    /// `i + 1` below it
+    pub quote_nonces_used: u64,
 }
 
 impl StoreAccount {
-    pub fn update_price(
-        account_info: &AccountInfo,
-        admin_account_info: &AccountInfo,
-        new_price: Price,
+    /// Maximum serialized size of the store state
+    pub const LEN: usize = 1
+        + Prices::LEN
+        + 32
+        + 33
+        + StoreRoles::LEN
+        + 1
+        + 1
+        + 32
+        + 1
+        + 1
+        + 32
+        + 8
+        + Catalog::LEN
+        + 33
+        + 8
+        + 33
+        + 2
+        + 32
+        + 8
+        + 2
+        + PricingMode::LEN
+        + 8
+        + 8;
+
+    /// Derive the address of the `index`-th store a creator opens for a mint
+    pub fn find_address(
+        program_id: &Pubkey,
+        creator: &Pubkey,
+        mint: &Pubkey,
+        index: u64,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                STORE_SEED_PREFIX,
+                creator.as_ref(),
+                mint.as_ref(),
+                &index.to_le_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Check that the store account lives at the address derived from its creator, mint and index
+    pub fn check_address(
+        &self,
+        program_id: &Pubkey,
+        store_account_info: &AccountInfo,
     ) -> ProgramResult {
-        let mut store_account: StoreAccount =
-            borsh::BorshDeserialize::try_from_slice(&account_info.data.borrow())?;
+        let address = Pubkey::create_program_address(
+            &[
+                STORE_SEED_PREFIX,
+                self.creator.as_ref(),
+                self.mint.as_ref(),
+                &self.index.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| SplStoreError::InvalidStoreAddress)?;
         ensure!(
-            store_account.admin == *admin_account_info.key,
-            SplStoreError::AccountNotAdmin.into()
+            address == *store_account_info.key,
+            SplStoreError::InvalidStoreAddress.into()
         );
-        store_account.price = new_price;
-        borsh::BorshSerialize::serialize(
-            &store_account,
-            &mut &mut account_info.data.borrow_mut()[..],
-        )?;
         Ok(())
     }
 
-    pub fn get_price(account_info: &AccountInfo) -> Result<Price, ProgramError> {
-        let store_account: StoreAccount =
-            borsh::BorshDeserialize::try_from_slice(&account_info.data.borrow())?;
-        Ok(store_account.price)
+    /// Check that `mint` is the store's own token
+    pub fn check_mint(&self, mint: &Pubkey) -> ProgramResult {
+        ensure!(*mint == self.mint, SplStoreError::StoreMintMismatch.into());
+        Ok(())
     }
 
-    /// /[writeable, signer] Funding account
-    /// /[writeable, signer] New store account
-    pub fn initialize_account(
-        program_id: &Pubkey,
-        account_infos: &[AccountInfo],
-        add_sol: Amount,
+    /// Check that `mint` is the store's own token, listed in its catalog or its quote mint
+    pub fn check_listed(&self, mint: &Pubkey) -> ProgramResult {
+        ensure!(
+            *mint == self.mint
+                || self.catalog.entry(mint).is_some()
+                || self.quote_mint == Some(*mint),
+            SplStoreError::MintNotListed.into()
+        );
+        Ok(())
+    }
+
+    /// Prices clients trade `mint` at, failing if the store does not trade it
+    pub fn trading_prices(&self, mint: &Pubkey) -> Result<Prices, ProgramError> {
+        if *mint == self.mint {
+            return Ok(self.prices);
+        }
+        match self.catalog.entry(mint) {
+            None => Err(SplStoreError::MintNotListed.into()),
+            Some(entry) if !entry.enabled => Err(SplStoreError::MintDisabled.into()),
+            Some(entry) => Ok(entry.prices),
+        }
+    }
+
+    /// How the store prices `mint`, catalog mints are always at their listed prices
+    pub fn mint_pricing_mode(&self, mint: &Pubkey) -> PricingMode {
+        match *mint == self.mint {
+            true => self.pricing_mode,
+            false => PricingMode::Fixed,
+        }
+    }
+
+    /// Record the base units sold on the bonding curve after a trade and save the store state
+    pub fn set_tokens_sold(
+        &mut self,
+        store_account_info: &AccountInfo,
+        tokens_sold: Amount,
     ) -> ProgramResult {
-        let account_infos_iter = &mut account_infos.iter();
+        ensure!(
+            store_account_info.is_writable,
+            SplStoreError::AccountNotWritable.into()
+        );
+        self.tokens_sold = tokens_sold;
+        self.pack(store_account_info)
+    }
 
-        let funding_account_info = next_account_info(account_infos_iter)?;
-        let store_account_info = next_account_info(account_infos_iter)?;
+    /// Derive the address of the vault holding the store's trading SOL
+    pub fn find_vault_address(program_id: &Pubkey, store: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[VAULT_SEED_PREFIX, store.as_ref()], program_id)
+    }
 
+    /// Check that the vault account is the one belonging to this store
+    pub fn check_vault(
+        &self,
+        program_id: &Pubkey,
+        store_account_info: &AccountInfo,
+        vault_account_info: &AccountInfo,
+    ) -> ProgramResult {
+        let address = Pubkey::create_program_address(
+            &[
+                VAULT_SEED_PREFIX,
+                store_account_info.key.as_ref(),
+                &[self.vault_bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| SplStoreError::InvalidVaultAddress)?;
         ensure!(
-            funding_account_info.is_writable,
-            SplStoreError::AccountNotWritable.into(),
+            address == *vault_account_info.key,
+            SplStoreError::InvalidVaultAddress.into()
         );
         ensure!(
-            funding_account_info.is_signer,
-            SplStoreError::AccountNotSigner.into(),
+            vault_account_info.owner == program_id,
+            ProgramError::IncorrectProgramId
         );
+        Ok(())
+    }
+
+    /// Lamports the vault can pay out while staying rent exempt and keeping the fees it holds
+    pub fn vault_available_lamports(&self, vault_account_info: &AccountInfo) -> u64 {
+        let fees = match self.quote_mint {
+            None => self.fees_collected,
+            Some(_) => 0,
+        };
+        vault_account_info
+            .lamports()
+            .saturating_sub(Rent::default().minimum_balance(0).saturating_add(fees))
+    }
+
+    /// Add the fee of a trade to the fees held by the store and save the store state
+    pub fn collect_fee(&mut self, store_account_info: &AccountInfo, fee: u64) -> ProgramResult {
+        if fee == 0 {
+            return Ok(());
+        }
         ensure!(
             store_account_info.is_writable,
-            SplStoreError::AccountNotWritable.into(),
+            SplStoreError::AccountNotWritable.into()
         );
+        self.fees_collected = self
+            .fees_collected
+            .checked_add(fee)
+            .ok_or(SplStoreError::MathOverflow)?;
+        self.pack(store_account_info)
+    }
+
+    /// Invoke an instruction with the store account as a signer
+    pub fn invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+    ) -> ProgramResult {
+        invoke_signed(
+            instruction,
+            account_infos,
+            &[&[
+                STORE_SEED_PREFIX,
+                self.creator.as_ref(),
+                self.mint.as_ref(),
+                &self.index.to_le_bytes(),
+                &[self.bump],
+            ]],
+        )
+    }
+
+    /// Deserialize store state without checking whether it was initialized
+    pub fn unpack_unchecked(account_info: &AccountInfo) -> Result<Self, ProgramError> {
+        ensure_len(account_info)?;
+        Ok(borsh::BorshDeserialize::deserialize(
+            &mut &account_info.data.borrow()[..],
+        )?)
+    }
+
+    /// Deserialize store state, failing if the store was never initialized
+    pub fn unpack(account_info: &AccountInfo) -> Result<Self, ProgramError> {
+        let store_account = StoreAccount::unpack_unchecked(account_info)?;
         ensure!(
-            store_account_info.is_signer,
-            SplStoreError::AccountNotSigner.into(),
+            store_account.is_initialized,
+            SplStoreError::StoreNotInitialized.into()
         );
+        Ok(store_account)
+    }
 
-        let space = size_of::<StoreAccount>();
-        let rent = Rent::default();
-        let lamports = rent.minimum_balance(space) + add_sol;
+    pub fn pack(&self, account_info: &AccountInfo) -> ProgramResult {
+        borsh::BorshSerialize::serialize(self, &mut &mut account_info.data.borrow_mut()[..])?;
+        Ok(())
+    }
 
+    /// Check that the given account is the store admin and approved the transaction,
+    /// either by signing or, for a multisig admin, through `signer_infos`
+    pub fn check_admin(
+        &self,
+        program_id: &Pubkey,
+        admin_account_info: &AccountInfo,
+        signer_infos: &[AccountInfo],
+    ) -> ProgramResult {
         ensure!(
-            rent.is_exempt(lamports, space),
-            ProgramError::AccountNotRentExempt
+            self.admin == *admin_account_info.key,
+            SplStoreError::AccountNotAdmin.into()
         );
+        ensure!(
+            admin_account_info.is_signer || Multisig::is_multisig(program_id, admin_account_info),
+            SplStoreError::AdminNotSigner.into()
+        );
+        validate_authority(program_id, admin_account_info, signer_infos)
+    }
 
-        let create_account_ix = create_account(
-            funding_account_info.key,
-            store_account_info.key,
-            lamports,
-            space as u64,
-            program_id,
+    /// Check that the given account is the admin or holds `role`, and approved the transaction
+    pub fn check_role(
+        &self,
+        program_id: &Pubkey,
+        role: Role,
+        authority_account_info: &AccountInfo,
+        signer_infos: &[AccountInfo],
+    ) -> ProgramResult {
+        ensure!(
+            self.admin == *authority_account_info.key
+                || self.roles.holder(role) == Some(*authority_account_info.key),
+            SplStoreError::AccountLacksRole.into()
         );
+        validate_authority(program_id, authority_account_info, signer_infos)
+    }
 
-        // [WRITE, SIGNER] Funding account
-        // [WRITE, SIGNER] New account
-        invoke(
-            &create_account_ix,
-            &[funding_account_info.clone(), store_account_info.clone()],
+    pub fn update_price(
+        program_id: &Pubkey,
+        account_info: &AccountInfo,
+        authority_account_info: &AccountInfo,
+        signer_infos: &[AccountInfo],
+        bid: Option<Price>,
+        ask: Option<Price>,
+    ) -> ProgramResult {
+        let mut store_account = StoreAccount::unpack(account_info)?;
+        store_account.check_role(
+            program_id,
+            Role::Pricer,
+            authority_account_info,
+            signer_infos,
         )?;
+        match store_account.pricing_mode {
+            PricingMode::Fixed => {}
+            PricingMode::Curve(_) => return Err(SplStoreError::PricedByCurve.into()),
+            PricingMode::ConstantProduct => return Err(SplStoreError::PricedByReserves.into()),
+        }
+        store_account.prices = store_account.prices.update(bid, ask)?;
+        store_account.pack(account_info)
+    }
 
-        Ok(())
+    pub fn get_prices(account_info: &AccountInfo) -> Result<Prices, ProgramError> {
+        Ok(StoreAccount::unpack(account_info)?.prices)
+    }
+
+    /// - \[writeable, signer] Funding account
+    /// - \[writeable] New store account
+    /// - [] System program account
+    pub fn initialize_account(
+        program_id: &Pubkey,
+        account_infos: &[AccountInfo],
+        signer_seeds: &[&[u8]],
+    ) -> ProgramResult {
+        initialize_pda(
+            program_id,
+            account_infos,
+            StoreAccount::LEN,
+            0,
+            signer_seeds,
+        )
+    }
+
+    /// - \[writeable, signer] Funding account
+    /// - \[writeable] New vault account
+    /// - [] System program account
+    pub fn initialize_vault(
+        program_id: &Pubkey,
+        account_infos: &[AccountInfo],
+        add_sol: Amount,
+        signer_seeds: &[&[u8]],
+    ) -> ProgramResult {
+        initialize_pda(program_id, account_infos, 0, add_sol, signer_seeds)
     }
 
     /// - \[writeable, signer] Funding account
@@ -122,8 +409,11 @@ impl StoreAccount {
         let system_program_account_info = next_account_info(account_infos_iter)?;
         let spl_token_program_account_info = next_account_info(account_infos_iter)?;
 
-        let expected_ata_pubkey =
-            get_associated_token_address(&wallet_account_info.key, &token_mint_account_info.key);
+        let expected_ata_pubkey = get_associated_token_address_with_program_id(
+            wallet_account_info.key,
+            token_mint_account_info.key,
+            spl_token_program_account_info.key,
+        );
 
         ensure!(
             system_program::check_id(&system_program_account_info.key),
@@ -172,3 +462,65 @@ impl StoreAccount {
         )
     }
 }
+
+fn ensure_len(account_info: &AccountInfo) -> ProgramResult {
+    ensure!(
+        account_info.data_len() == StoreAccount::LEN,
+        ProgramError::InvalidAccountData
+    );
+    Ok(())
+}
+
+/// - \[writeable, signer] Funding account
+/// - \[writeable] New account
+/// - [] System program account
+fn initialize_pda(
+    program_id: &Pubkey,
+    account_infos: &[AccountInfo],
+    space: usize,
+    add_sol: Amount,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let account_infos_iter = &mut account_infos.iter();
+
+    let funding_account_info = next_account_info(account_infos_iter)?;
+    let new_account_info = next_account_info(account_infos_iter)?;
+    let system_program_account_info = next_account_info(account_infos_iter)?;
+
+    ensure!(
+        system_program::check_id(system_program_account_info.key),
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        funding_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into(),
+    );
+    ensure!(
+        funding_account_info.is_signer,
+        SplStoreError::AccountNotSigner.into(),
+    );
+    ensure!(
+        new_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into(),
+    );
+
+    let rent = Rent::default();
+    let lamports = rent.minimum_balance(space) + add_sol;
+
+    ensure!(
+        rent.is_exempt(lamports, space),
+        ProgramError::AccountNotRentExempt
+    );
+
+    create_pda_account(
+        &[
+            funding_account_info.clone(),
+            new_account_info.clone(),
+            system_program_account_info.clone(),
+        ],
+        lamports,
+        space,
+        program_id,
+        signer_seeds,
+    )
+}
@@ -0,0 +1,62 @@
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use spl_token::solana_program::{entrypoint::ProgramResult, pubkey::Pubkey};
+
+use crate::{ensure, error::SplStoreError, store::prices::Prices};
+
+/// Maximum number of mints a store can list next to its own token
+pub const MAX_CATALOG_ENTRIES: usize = 8;
+
+/// Mint traded by the store next to its own token, kept in the ATA of the store
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct CatalogEntry {
+    pub mint: Pubkey,
+    pub prices: Prices,
+    /// Whether clients may trade the mint
+    pub enabled: bool,
+}
+
+impl CatalogEntry {
+    /// Serialized size of an entry
+    pub const LEN: usize = 32 + Prices::LEN + 1;
+}
+
+#[derive(Debug, Default, BorshSerialize, BorshDeserialize)]
+pub struct Catalog {
+    pub entries: Vec<CatalogEntry>,
+}
+
+impl Catalog {
+    /// Maximum serialized size of the catalog
+    pub const LEN: usize = 4 + MAX_CATALOG_ENTRIES * CatalogEntry::LEN;
+
+    pub fn entry(&self, mint: &Pubkey) -> Option<&CatalogEntry> {
+        self.entries.iter().find(|entry| entry.mint == *mint)
+    }
+
+    pub fn entry_mut(&mut self, mint: &Pubkey) -> Option<&mut CatalogEntry> {
+        self.entries.iter_mut().find(|entry| entry.mint == *mint)
+    }
+
+    pub fn add(&mut self, entry: CatalogEntry) -> ProgramResult {
+        ensure!(
+            self.entry(&entry.mint).is_none(),
+            SplStoreError::MintAlreadyListed.into()
+        );
+        ensure!(
+            self.entries.len() < MAX_CATALOG_ENTRIES,
+            SplStoreError::CatalogFull.into()
+        );
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, mint: &Pubkey) -> ProgramResult {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.mint != *mint);
+        ensure!(
+            self.entries.len() < len,
+            SplStoreError::MintNotListed.into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+//! Settlement of trades in the quote mint of a store, for stores that do not trade against SOL
+
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program::invoke,
+    program_error::ProgramError,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, Amount},
+    utils::{check_ata_mint, check_token_program, pre_fee_amount, token_amount, transfer_checked},
+};
+
+/// Token accounts a trade moves the quote mint between
+pub struct QuoteMintAccounts<'a, 'b> {
+    pub store_ata: &'a AccountInfo<'b>,
+    pub client_ata: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+    /// Token program owning the quote mint, not necessarily the one of the traded mint
+    pub token_program: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> QuoteMintAccounts<'a, 'b> {
+    /// Take the quote mint accounts of a trade, if the store has a quote mint
+    /// - \[writeable] Store ATA of the quote mint
+    /// - \[writeable] Client ATA of the quote mint
+    /// - [] Quote mint account
+    /// - [] SPL Token or Token-2022 program account, owner of the quote mint
+    pub fn next<I: Iterator<Item = &'a AccountInfo<'b>>>(
+        store_account: &StoreAccount,
+        store_account_info: &AccountInfo,
+        client_account_info: &AccountInfo,
+        accounts_info_iter: &mut I,
+    ) -> Result<Option<Self>, ProgramError> {
+        let Some(quote_mint) = store_account.quote_mint else {
+            return Ok(None);
+        };
+        let store_ata = next_account_info(accounts_info_iter)?;
+        let client_ata = next_account_info(accounts_info_iter)?;
+        let mint = next_account_info(accounts_info_iter)?;
+        let token_program = next_account_info(accounts_info_iter)?;
+
+        ensure!(
+            *mint.key == quote_mint,
+            SplStoreError::QuoteMintMismatch.into()
+        );
+        check_token_program(token_program, mint)?;
+        for (ata, wallet) in [
+            (store_ata, store_account_info.key),
+            (client_ata, client_account_info.key),
+        ] {
+            ensure!(
+                get_associated_token_address_with_program_id(wallet, mint.key, token_program.key,)
+                    == *ata.key,
+                SplStoreError::InvalidAtaAddress.into()
+            );
+            ensure!(ata.is_writable, SplStoreError::AccountNotWritable.into());
+        }
+        Ok(Some(Self {
+            store_ata,
+            client_ata,
+            mint,
+            token_program,
+        }))
+    }
+
+    /// Base units of the quote mint the store holds for trading, that is without its fees
+    pub fn store_reserve(&self, store_account: &StoreAccount) -> Result<Amount, ProgramError> {
+        if self.store_ata.lamports() == 0 {
+            return Ok(0);
+        }
+        Ok(token_amount(self.store_ata)?.saturating_sub(store_account.fees_collected))
+    }
+
+    /// Move `amount` base units of the quote mint from the store to the client,
+    /// leaving the fees the store holds. Returns the amount the client received,
+    /// less a transfer fee charged by the quote mint
+    pub fn pay_client(
+        &self,
+        store_account: &StoreAccount,
+        store_account_info: &AccountInfo<'b>,
+        amount: Amount,
+    ) -> Result<Amount, ProgramError> {
+        check_ata_mint(self.client_ata, self.mint)?;
+        let client_balance = token_amount(self.client_ata)?;
+        self.pay(store_account, store_account_info, self.client_ata, amount)?;
+        Ok(token_amount(self.client_ata)?
+            .checked_sub(client_balance)
+            .ok_or(SplStoreError::MathOverflow)?)
+    }
+
+    /// Move `amount` base units of the quote mint from the store to a token account
+    /// of the referrer of the trade, other than the client's, leaving the fees the store holds
+    pub fn pay_referrer(
+        &self,
+        store_account: &StoreAccount,
+        store_account_info: &AccountInfo<'b>,
+        referrer_account_info: &AccountInfo<'b>,
+        amount: Amount,
+    ) -> ProgramResult {
+        check_ata_mint(referrer_account_info, self.mint)?;
+        ensure!(
+            referrer_account_info.key != self.client_ata.key,
+            SplStoreError::InvalidReferrer.into()
+        );
+        self.pay(
+            store_account,
+            store_account_info,
+            referrer_account_info,
+            amount,
+        )
+    }
+
+    fn pay(
+        &self,
+        store_account: &StoreAccount,
+        store_account_info: &AccountInfo<'b>,
+        destination_account_info: &AccountInfo<'b>,
+        amount: Amount,
+    ) -> ProgramResult {
+        check_ata_mint(self.store_ata, self.mint)?;
+        ensure!(
+            token_amount(self.store_ata)?.saturating_sub(store_account.fees_collected) >= amount,
+            SplStoreError::InsufficientFundsForTransaction.into()
+        );
+
+        let transfer_ix = transfer_checked(
+            self.token_program,
+            self.store_ata,
+            self.mint,
+            destination_account_info,
+            store_account_info,
+            amount,
+        )?;
+        // [writable] The source account.
+        // [] The token mint.
+        // [writable] The destination account.
+        // [signer] The source account’s owner/delegate.
+        store_account.invoke_signed(
+            &transfer_ix,
+            &[
+                self.store_ata.clone(),
+                self.mint.clone(),
+                destination_account_info.clone(),
+                store_account_info.clone(),
+            ],
+        )
+    }
+
+    /// Move `amount` base units of the quote mint from the client to the store, the client
+    /// pays a transfer fee charged by the quote mint on top. Returns the amount charged
+    pub fn charge_client(
+        &self,
+        client_account_info: &AccountInfo<'b>,
+        amount: Amount,
+    ) -> Result<Amount, ProgramError> {
+        check_ata_mint(self.store_ata, self.mint)?;
+        check_ata_mint(self.client_ata, self.mint)?;
+        let charged = pre_fee_amount(self.mint, amount)?;
+        ensure!(
+            token_amount(self.client_ata)? >= charged,
+            SplStoreError::InsufficientFundsForTransaction.into()
+        );
+
+        let store_balance = token_amount(self.store_ata)?;
+        let transfer_ix = transfer_checked(
+            self.token_program,
+            self.client_ata,
+            self.mint,
+            self.store_ata,
+            client_account_info,
+            charged,
+        )?;
+        // [writable] The source account.
+        // [] The token mint.
+        // [writable] The destination account.
+        // [signer] The source account’s owner/delegate.
+        invoke(
+            &transfer_ix,
+            &[
+                self.client_ata.clone(),
+                self.mint.clone(),
+                self.store_ata.clone(),
+                client_account_info.clone(),
+            ],
+        )?;
+        // The store is credited what arrived, not what was sent
+        let received = token_amount(self.store_ata)?
+            .checked_sub(store_balance)
+            .ok_or(SplStoreError::MathOverflow)?;
+        ensure!(
+            received >= amount,
+            SplStoreError::InsufficientFundsForTransaction.into()
+        );
+        Ok(charged)
+    }
+}
@@ -0,0 +1,203 @@
+//! Bonding curves pricing the store's own token by the number of tokens it sold.
+//! A trade is worth the area under the curve between the tokens sold before and after it,
+//! so buying back tokens walks the curve down the way selling them walked it up.
+//! Like all trade arithmetic the store rounds in its favour.
+
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use spl_token::solana_program::{entrypoint::ProgramResult, program_error::ProgramError};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{
+        pricing::{Rounding, BPS_DENOMINATOR},
+        Amount, Price,
+    },
+};
+
+/// Fixed point scale of the growth factor of exponential curves
+const WAD: u128 = 1_000_000_000_000;
+
+/// How the store prices its own token. Catalog mints keep their listed prices
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum PricingMode {
+    /// At the bid and ask set by the admin and the pricer
+    #[default]
+    Fixed,
+    /// On a bonding curve, clients buy and sell at the same price
+    Curve(Curve),
+    /// From the reserves of the store, its own token and SOL or the quote mint, keeping their
+    /// product constant. Deposits and withdrawals of the treasury move the price
+    ConstantProduct,
+}
+
+impl PricingMode {
+    /// Serialized size of the largest pricing mode
+    pub const LEN: usize = 1 + Curve::LEN;
+}
+
+/// Price of the store's own token as a function of the base units it sold,
+/// in lamports per whole token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum Curve {
+    /// Starts at `base_price` and rises by `price_step` for every `step_interval` base units sold
+    Linear {
+        base_price: Price,
+        price_step: Price,
+        step_interval: Amount,
+    },
+    /// Starts at `base_price` and grows by `growth_bps` basis points for every `step_interval`
+    /// base units sold, staying constant in between
+    Exponential {
+        base_price: Price,
+        growth_bps: u16,
+        step_interval: Amount,
+    },
+}
+
+impl Curve {
+    /// Serialized size of the largest curve
+    pub const LEN: usize = 1 + 8 + 8 + 8;
+
+    /// Check that the curve can be evaluated
+    pub fn validate(self) -> ProgramResult {
+        let valid = match self {
+            Curve::Linear { step_interval, .. } => step_interval != 0,
+            Curve::Exponential {
+                growth_bps,
+                step_interval,
+                ..
+            } => step_interval != 0 && growth_bps != 0,
+        };
+        ensure!(valid, SplStoreError::InvalidCurve.into());
+        Ok(())
+    }
+
+    /// Lamports a client pays for the `amount` base units the store sells after
+    /// `tokens_sold`, rounded up
+    pub fn sell_lamports(
+        self,
+        tokens_sold: Amount,
+        amount: Amount,
+        decimals: u8,
+    ) -> Result<u64, ProgramError> {
+        let end = tokens_sold
+            .checked_add(amount)
+            .ok_or(SplStoreError::MathOverflow)?;
+        self.lamports(tokens_sold, end, decimals, Rounding::Up)
+    }
+
+    /// Lamports the store pays for buying back `amount` of the `tokens_sold` base units,
+    /// rounded down
+    pub fn buy_lamports(
+        self,
+        tokens_sold: Amount,
+        amount: Amount,
+        decimals: u8,
+    ) -> Result<u64, ProgramError> {
+        let start = tokens_sold
+            .checked_sub(amount)
+            .ok_or(SplStoreError::CurveSupplyExceeded)?;
+        self.lamports(start, tokens_sold, decimals, Rounding::Down)
+    }
+
+    /// Area under the curve between `start` and `end` base units sold
+    fn lamports(
+        self,
+        start: Amount,
+        end: Amount,
+        decimals: u8,
+        rounding: Rounding,
+    ) -> Result<u64, ProgramError> {
+        let scale = 10u128
+            .checked_pow(decimals.into())
+            .ok_or(SplStoreError::MathOverflow)?;
+        let (end_area, denominator) = self.area(end, scale)?;
+        let (start_area, _) = self.area(start, scale)?;
+        let area = end_area
+            .checked_sub(start_area)
+            .ok_or(SplStoreError::MathOverflow)?;
+        let lamports = match rounding {
+            Rounding::Up => area.div_ceil(denominator),
+            Rounding::Down => area / denominator,
+        };
+        u64::try_from(lamports).map_err(|_| SplStoreError::MathOverflow.into())
+    }
+
+    /// Area under the curve up to `tokens_sold` base units, as a numerator and a denominator
+    /// that only depends on the curve and `scale`, the base units of a whole token
+    fn area(self, tokens_sold: Amount, scale: u128) -> Result<(u128, u128), ProgramError> {
+        let sold = u128::from(tokens_sold);
+        match self {
+            // base * sold / scale + step * sold^2 / (2 * interval * scale)
+            Curve::Linear {
+                base_price,
+                price_step,
+                step_interval,
+            } => {
+                let interval = u128::from(step_interval);
+                let base_area = checked_product(&[2, interval, base_price.into(), sold])?;
+                let step_area = checked_product(&[price_step.into(), sold, sold])?;
+                Ok((
+                    checked_sum(base_area, step_area)?,
+                    checked_product(&[2, interval, scale])?,
+                ))
+            }
+            // Steps with prices base * growth^k, where the price of a step is the increase of
+            // the growth factor over it scaled by 1 / (growth - 1), so the areas of whole steps
+            // add up to exactly the geometric sum and no rounding is carried across steps
+            Curve::Exponential {
+                base_price,
+                growth_bps,
+                step_interval,
+            } => {
+                let steps = tokens_sold / step_interval;
+                let rest = u128::from(tokens_sold % step_interval);
+                let factor = growth_factor(growth_bps, steps)?;
+                let next_step = steps.checked_add(1).ok_or(SplStoreError::MathOverflow)?;
+                let next_factor = growth_factor(growth_bps, next_step)?;
+                let whole_steps =
+                    checked_product(&[step_interval.into(), factor.saturating_sub(WAD)])?;
+                let partial_step = checked_product(&[rest, next_factor.saturating_sub(factor)])?;
+                Ok((
+                    checked_product(&[
+                        base_price.into(),
+                        BPS_DENOMINATOR.into(),
+                        checked_sum(whole_steps, partial_step)?,
+                    ])?,
+                    checked_product(&[growth_bps.into(), WAD, scale])?,
+                ))
+            }
+        }
+    }
+}
+
+/// `(1 + growth_bps / 10000)^steps`, scaled by `WAD` and rounded down
+fn growth_factor(growth_bps: u16, steps: u64) -> Result<u128, ProgramError> {
+    let denominator = u128::from(BPS_DENOMINATOR);
+    let mut power = WAD * (denominator + u128::from(growth_bps)) / denominator;
+    let mut factor = WAD;
+    let mut steps = steps;
+    while steps != 0 {
+        if steps & 1 == 1 {
+            factor = checked_product(&[factor, power])? / WAD;
+        }
+        steps >>= 1;
+        if steps != 0 {
+            power = checked_product(&[power, power])? / WAD;
+        }
+    }
+    Ok(factor)
+}
+
+fn checked_product(factors: &[u128]) -> Result<u128, ProgramError> {
+    factors.iter().try_fold(1u128, |product, factor| {
+        product
+            .checked_mul(*factor)
+            .ok_or(SplStoreError::MathOverflow.into())
+    })
+}
+
+fn checked_sum(a: u128, b: u128) -> Result<u128, ProgramError> {
+    a.checked_add(b).ok_or(SplStoreError::MathOverflow.into())
+}
@@ -1,3 +1,15 @@
 pub mod account;
+pub mod catalog;
+pub mod currency;
+pub mod curve;
+pub mod multisig;
+pub mod prices;
+pub mod pricing;
+pub mod quote;
+pub mod referral;
+pub mod role;
+pub mod wrapped_sol;
+/// Lamports per whole token, that is per `10^decimals` base units of the mint.
+/// For a store with a quote mint, base units of the quote mint instead of lamports
 pub type Price = u64;
 pub type Amount = u64;
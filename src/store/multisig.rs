@@ -0,0 +1,81 @@
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use spl_token::solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{ensure, error::SplStoreError};
+
+/// Maximum number of multisig signers
+pub const MAX_SIGNERS: usize = 11;
+
+/// M-of-N set of keys that can act as a store authority in place of a single key
+#[derive(Debug, Default, BorshSerialize, BorshDeserialize)]
+pub struct Multisig {
+    pub is_initialized: bool,
+    /// Number of signers required
+    pub m: u8,
+    /// Number of valid signers
+    pub n: u8,
+    pub signers: [Pubkey; MAX_SIGNERS],
+}
+
+impl Multisig {
+    /// Serialized size of the multisig state
+    pub const LEN: usize = 1 + 1 + 1 + 32 * MAX_SIGNERS;
+
+    /// Whether the account looks like a multisig owned by this program
+    pub fn is_multisig(program_id: &Pubkey, account_info: &AccountInfo) -> bool {
+        account_info.owner == program_id && account_info.data_len() == Multisig::LEN
+    }
+
+    pub fn unpack(account_info: &AccountInfo) -> Result<Self, ProgramError> {
+        let multisig: Multisig =
+            borsh::BorshDeserialize::try_from_slice(&account_info.data.borrow())?;
+        ensure!(multisig.is_initialized, ProgramError::UninitializedAccount);
+        Ok(multisig)
+    }
+
+    pub fn pack(&self, account_info: &AccountInfo) -> ProgramResult {
+        borsh::BorshSerialize::serialize(self, &mut &mut account_info.data.borrow_mut()[..])?;
+        Ok(())
+    }
+
+    /// Check that at least `m` distinct configured signers are among `signer_infos`
+    pub fn check_signers(&self, signer_infos: &[AccountInfo]) -> ProgramResult {
+        let signers = &self.signers[..self.n as usize];
+        let mut matched = [false; MAX_SIGNERS];
+        let mut num_signers = 0;
+        for signer_info in signer_infos.iter().filter(|info| info.is_signer) {
+            for (position, key) in signers.iter().enumerate() {
+                if key == signer_info.key && !matched[position] {
+                    matched[position] = true;
+                    num_signers += 1;
+                }
+            }
+        }
+        ensure!(
+            num_signers >= self.m,
+            SplStoreError::NotEnoughMultisigSigners.into()
+        );
+        Ok(())
+    }
+}
+
+/// Check that an authority approved the transaction: a plain key must sign itself,
+/// a multisig needs `m` of its signers among `signer_infos`
+pub fn validate_authority(
+    program_id: &Pubkey,
+    authority_info: &AccountInfo,
+    signer_infos: &[AccountInfo],
+) -> ProgramResult {
+    if Multisig::is_multisig(program_id, authority_info) {
+        Multisig::unpack(authority_info)?.check_signers(signer_infos)
+    } else {
+        ensure!(
+            authority_info.is_signer,
+            SplStoreError::AuthorityNotSigner.into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use spl_token::solana_program::program_error::ProgramError;
+
+use crate::{ensure, error::SplStoreError, store::Price};
+
+/// Prices a store trades a mint at, the difference is the store's spread
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Prices {
+    /// Price the store pays when buying tokens from a client
+    pub bid: Price,
+    /// Price a client pays when buying tokens from the store
+    pub ask: Price,
+}
+
+impl Prices {
+    /// Serialized size of the prices
+    pub const LEN: usize = 8 + 8;
+
+    /// Check that the store never buys above the price it sells at
+    pub fn validate(self) -> Result<Self, ProgramError> {
+        ensure!(self.bid <= self.ask, SplStoreError::BidExceedsAsk.into());
+        Ok(self)
+    }
+
+    /// Replace the given sides, keeping the others
+    pub fn update(self, bid: Option<Price>, ask: Option<Price>) -> Result<Self, ProgramError> {
+        Prices {
+            bid: bid.unwrap_or(self.bid),
+            ask: ask.unwrap_or(self.ask),
+        }
+        .validate()
+    }
+}
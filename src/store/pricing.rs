@@ -0,0 +1,101 @@
+//! Trade arithmetic. Intermediate values are computed in u128 so only results
+//! that do not fit into lamports fail. Whenever a trade does not come out in
+//! whole lamports the remainder goes to the store: lamports the store pays are
+//! rounded down, lamports it receives are rounded up.
+
+use spl_token::solana_program::program_error::ProgramError;
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{Amount, Price},
+};
+
+/// Basis points making up the whole of a value
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+/// Direction to round a fractional lamport amount to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// Lamports the store pays for buying `amount` base units at `price`, rounded down
+pub fn buy_lamports(amount: Amount, price: Price, decimals: u8) -> Result<u64, ProgramError> {
+    lamports(amount, price, decimals, Rounding::Down)
+}
+
+/// Lamports a client pays for buying `amount` base units from the store at `price`, rounded up
+pub fn sell_lamports(amount: Amount, price: Price, decimals: u8) -> Result<u64, ProgramError> {
+    lamports(amount, price, decimals, Rounding::Up)
+}
+
+/// Value of `amount` base units of a mint with `decimals` at `price` lamports per whole token
+pub fn lamports(
+    amount: Amount,
+    price: Price,
+    decimals: u8,
+    rounding: Rounding,
+) -> Result<u64, ProgramError> {
+    // Two u64 factors always fit into u128
+    let value = u128::from(amount) * u128::from(price);
+    // A scale beyond u128 exceeds every value, so the quotient is zero
+    let (quotient, remainder) = match 10u128.checked_pow(decimals.into()) {
+        Some(scale) => (value / scale, value % scale),
+        None => (0, value),
+    };
+    let lamports = match rounding {
+        Rounding::Up if remainder != 0 => quotient + 1,
+        _ => quotient,
+    };
+    u64::try_from(lamports).map_err(|_| SplStoreError::MathOverflow.into())
+}
+
+/// Fee of `fee_bps` basis points on `value`, rounded up like all amounts the store receives
+pub fn fee(value: u64, fee_bps: u16) -> Result<u64, ProgramError> {
+    let fee = u128::from(value) * u128::from(fee_bps);
+    let denominator = u128::from(BPS_DENOMINATOR);
+    u64::try_from(fee.div_ceil(denominator)).map_err(|_| SplStoreError::MathOverflow.into())
+}
+
+/// Share of `referral_bps` basis points of `fee` paid to a referrer, rounded down
+/// like all amounts the store pays
+pub fn referral_reward(fee: u64, referral_bps: u16) -> Result<u64, ProgramError> {
+    let reward = u128::from(fee) * u128::from(referral_bps);
+    let denominator = u128::from(BPS_DENOMINATOR);
+    u64::try_from(reward / denominator).map_err(|_| SplStoreError::MathOverflow.into())
+}
+
+/// Lamports the store pays for `amount` base units added to its `token_reserve`, keeping
+/// the product of its reserves constant, rounded down
+pub fn reserves_buy_lamports(
+    sol_reserve: u64,
+    token_reserve: Amount,
+    amount: Amount,
+) -> Result<u64, ProgramError> {
+    ensure!(
+        sol_reserve != 0 && token_reserve != 0,
+        SplStoreError::InsufficientReserves.into()
+    );
+    // sol * amount / (tokens + amount) never exceeds the SOL reserve
+    let value = u128::from(sol_reserve) * u128::from(amount);
+    let tokens = u128::from(token_reserve) + u128::from(amount);
+    u64::try_from(value / tokens).map_err(|_| SplStoreError::MathOverflow.into())
+}
+
+/// Lamports a client pays for `amount` base units taken out of the store's `token_reserve`,
+/// keeping the product of its reserves constant, rounded up
+pub fn reserves_sell_lamports(
+    sol_reserve: u64,
+    token_reserve: Amount,
+    amount: Amount,
+) -> Result<u64, ProgramError> {
+    ensure!(
+        sol_reserve != 0 && amount < token_reserve,
+        SplStoreError::InsufficientReserves.into()
+    );
+    let value = u128::from(sol_reserve) * u128::from(amount);
+    let tokens = u128::from(token_reserve - amount);
+    u64::try_from(value.div_ceil(tokens)).map_err(|_| SplStoreError::MathOverflow.into())
+}
@@ -0,0 +1,156 @@
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use spl_token::solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    ed25519_program,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::{Pubkey, PUBKEY_BYTES},
+    sysvar::{
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+        Sysvar,
+    },
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, Amount, Price},
+};
+
+/// Offset of the signature offsets in Ed25519 program instruction data,
+/// after the signature count and a padding byte
+const ED25519_OFFSETS_START: usize = 2;
+/// Serialized size of the signature offsets of one signature
+const ED25519_OFFSETS_LEN: usize = 14;
+/// Instruction index the Ed25519 program reads as "this instruction"
+const ED25519_CURRENT_INSTRUCTION: u16 = u16::MAX;
+/// Number of nonces below the greatest accepted one that quotes can still use,
+/// so quotes landing out of order are not refused
+pub const QUOTE_NONCE_WINDOW: u64 = u64::BITS as u64;
+
+/// Trade direction a quote is valid for, seen from the store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum QuoteSide {
+    /// The store buys tokens from the client at the quoted price
+    Buy,
+    /// The store sells tokens to the client at the quoted price
+    Sell,
+}
+
+/// Price the quote signer offers for a single trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Quote {
+    pub price: Price,
+    pub amount: Amount,
+    /// Last slot the quote can be used in
+    pub expiry_slot: u64,
+    /// Used once, at most `QUOTE_NONCE_WINDOW` below the greatest nonce the store accepted
+    pub nonce: u64,
+}
+
+/// Borsh serialized, this is the message the quote signer signs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct QuoteMessage {
+    pub side: QuoteSide,
+    pub store: Pubkey,
+    pub mint: Pubkey,
+    /// Only this client can trade on the quote
+    pub client: Pubkey,
+    /// Store fee the quoted price was offered with, the quote is refused once it changed
+    pub fee_bps: u16,
+    pub quote: Quote,
+}
+
+/// Check that the quote signer of the store signed `message` in the Ed25519 program
+/// instruction preceding the current one, that the quote has not expired and that its
+/// nonce was not used before, then record the nonce
+pub fn accept_quote(
+    store_account: &mut StoreAccount,
+    message: &QuoteMessage,
+    instructions_sysvar_info: &AccountInfo,
+) -> ProgramResult {
+    let quote_signer = store_account
+        .quote_signer
+        .ok_or(SplStoreError::QuoteSignerNotSet)?;
+    ensure!(
+        Clock::get()?.slot <= message.quote.expiry_slot,
+        SplStoreError::QuoteExpired.into()
+    );
+
+    let current_index = load_current_index_checked(instructions_sysvar_info)?;
+    let index = current_index
+        .checked_sub(1)
+        .ok_or(SplStoreError::InvalidQuoteSignature)?;
+    let instruction = load_instruction_at_checked(index.into(), instructions_sysvar_info)?;
+    ensure!(
+        ed25519_program::check_id(&instruction.program_id),
+        SplStoreError::InvalidQuoteSignature.into()
+    );
+    let (public_key, signed_message) = ed25519_signed_data(&instruction.data)?;
+    let expected_message = borsh::BorshSerialize::try_to_vec(message)?;
+    ensure!(
+        public_key == quote_signer.as_ref() && signed_message == expected_message.as_slice(),
+        SplStoreError::InvalidQuoteSignature.into()
+    );
+
+    use_nonce(store_account, message.quote.nonce)
+}
+
+/// Record a quote nonce, failing if it was used before or fell out of the window
+fn use_nonce(store_account: &mut StoreAccount, nonce: u64) -> ProgramResult {
+    let last_nonce = store_account.last_quote_nonce;
+    if nonce > last_nonce {
+        // Bit `i` stands for the nonce `i + 1` below the greatest one
+        let shift = (nonce - last_nonce).min(QUOTE_NONCE_WINDOW + 1) as u32;
+        let used = store_account
+            .quote_nonces_used
+            .checked_shl(shift)
+            .unwrap_or(0);
+        store_account.quote_nonces_used = used | 1u64.checked_shl(shift - 1).unwrap_or(0);
+        store_account.last_quote_nonce = nonce;
+    } else {
+        let offset = last_nonce - nonce;
+        ensure!(
+            offset != 0 && offset <= QUOTE_NONCE_WINDOW,
+            SplStoreError::QuoteNonceUsed.into()
+        );
+        let bit = 1 << (offset - 1);
+        ensure!(
+            store_account.quote_nonces_used & bit == 0,
+            SplStoreError::QuoteNonceUsed.into()
+        );
+        store_account.quote_nonces_used |= bit;
+    }
+    Ok(())
+}
+
+/// Public key and message of an Ed25519 program instruction verifying a single
+/// signature over data contained in the instruction itself
+fn ed25519_signed_data(data: &[u8]) -> Result<(&[u8], &[u8]), ProgramError> {
+    let offsets_end = ED25519_OFFSETS_START + ED25519_OFFSETS_LEN;
+    ensure!(
+        data.len() >= offsets_end && data[0] == 1,
+        SplStoreError::InvalidQuoteSignature.into()
+    );
+    let [_, signature_index, public_key_offset, public_key_index, message_offset, message_len, message_index] =
+        [0, 1, 2, 3, 4, 5, 6].map(|field| {
+            let start = ED25519_OFFSETS_START + 2 * field;
+            u16::from_le_bytes([data[start], data[start + 1]])
+        });
+    ensure!(
+        [signature_index, public_key_index, message_index]
+            .iter()
+            .all(|index| *index == ED25519_CURRENT_INSTRUCTION),
+        SplStoreError::InvalidQuoteSignature.into()
+    );
+
+    let public_key_offset = usize::from(public_key_offset);
+    let message_offset = usize::from(message_offset);
+    let public_key = data.get(public_key_offset..public_key_offset + PUBKEY_BYTES);
+    let message = data.get(message_offset..message_offset + usize::from(message_len));
+    match (public_key, message) {
+        (Some(public_key), Some(message)) => Ok((public_key, message)),
+        _ => Err(SplStoreError::InvalidQuoteSignature.into()),
+    }
+}
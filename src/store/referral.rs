@@ -0,0 +1,71 @@
+//! Rewards of referrers routing trades to a store, paid out of the fee of the trade
+
+use spl_token::solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, currency::QuoteMintAccounts, Amount},
+    utils::move_lamports,
+};
+
+/// Take the referrer account trailing the accounts of a trade, if there is one.
+/// Executable accounts, like programs passed along for cross-program invocations,
+/// are skipped. The client, or whoever funds the trade, cannot refer it
+/// - \[writeable] Referrer account - receives lamports or, with a quote mint,
+///   is a token account of the quote mint
+pub fn next_referrer<'a, 'b, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    vault_account_info: &AccountInfo,
+    funding_account_info: &AccountInfo,
+    client_account_info: &AccountInfo,
+    accounts_info_iter: &mut I,
+) -> Result<Option<&'a AccountInfo<'b>>, ProgramError> {
+    let Some(referrer_account_info) =
+        accounts_info_iter.find(|account_info| !account_info.executable)
+    else {
+        return Ok(None);
+    };
+    ensure!(
+        referrer_account_info.is_writable,
+        SplStoreError::AccountNotWritable.into()
+    );
+    ensure!(
+        referrer_account_info.key != vault_account_info.key
+            && referrer_account_info.key != funding_account_info.key
+            && referrer_account_info.key != client_account_info.key,
+        SplStoreError::InvalidReferrer.into()
+    );
+    Ok(Some(referrer_account_info))
+}
+
+/// Pay the referrer its `reward` in the currency the trade settled in
+pub fn pay_referrer<'b>(
+    store_account: &StoreAccount,
+    store_account_info: &AccountInfo<'b>,
+    vault_account_info: &AccountInfo<'b>,
+    referrer_account_info: &AccountInfo<'b>,
+    quote_mint_accounts: Option<&QuoteMintAccounts<'_, 'b>>,
+    reward: Amount,
+) -> ProgramResult {
+    if reward == 0 {
+        return Ok(());
+    }
+    match quote_mint_accounts {
+        None => {
+            move_lamports(vault_account_info, referrer_account_info, reward)?;
+            msg!("Store Vault ==[{} lamports]==> Referrer", reward);
+        }
+        Some(quote_mint_accounts) => {
+            quote_mint_accounts.pay_referrer(
+                store_account,
+                store_account_info,
+                referrer_account_info,
+                reward,
+            )?;
+            msg!("Store quote ATA ==[{} tokens]==> Referrer", reward);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use spl_token::solana_program::pubkey::Pubkey;
+
+/// Privileges that the store admin can delegate to other keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum Role {
+    /// May update prices
+    Pricer,
+    /// May withdraw store funds
+    Treasurer,
+    /// May pause and resume trading
+    Pauser,
+    /// May grant and revoke every role except its own
+    RoleManager,
+}
+
+/// Current holder of each role, the admin implicitly holds all of them
+#[derive(Debug, Default, BorshSerialize, BorshDeserialize)]
+pub struct StoreRoles {
+    pub pricer: Option<Pubkey>,
+    pub treasurer: Option<Pubkey>,
+    pub pauser: Option<Pubkey>,
+    pub role_manager: Option<Pubkey>,
+}
+
+impl StoreRoles {
+    /// Maximum serialized size of the roles
+    pub const LEN: usize = 4 * 33;
+
+    pub fn holder(&self, role: Role) -> Option<Pubkey> {
+        *self.slot(role)
+    }
+
+    pub fn set_holder(&mut self, role: Role, holder: Option<Pubkey>) {
+        *self.slot_mut(role) = holder;
+    }
+
+    fn slot(&self, role: Role) -> &Option<Pubkey> {
+        match role {
+            Role::Pricer => &self.pricer,
+            Role::Treasurer => &self.treasurer,
+            Role::Pauser => &self.pauser,
+            Role::RoleManager => &self.role_manager,
+        }
+    }
+
+    fn slot_mut(&mut self, role: Role) -> &mut Option<Pubkey> {
+        match role {
+            Role::Pricer => &mut self.pricer,
+            Role::Treasurer => &mut self.treasurer,
+            Role::Pauser => &mut self.pauser,
+            Role::RoleManager => &mut self.role_manager,
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Settlement of SOL trades through wrapped SOL token accounts of clients, for clients
+//! that hold SOL only as wrapped SOL and cannot sign debits of raw lamports
+
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token::solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::AccountMeta,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    instruction::{close_account, sync_native},
+    state::Account,
+};
+
+use crate::{
+    ensure,
+    error::SplStoreError,
+    store::{account::StoreAccount, Amount},
+    utils::{move_lamports, transfer_checked},
+};
+
+/// Mint of the wrapped SOL accounts of the token program
+pub fn native_mint(token_program_id: &Pubkey) -> Pubkey {
+    if spl_token_2022::check_id(token_program_id) {
+        spl_token_2022::native_mint::id()
+    } else {
+        spl_token::native_mint::id()
+    }
+}
+
+/// Check that the account is a wrapped SOL account of the token program
+pub fn check_wrapped_sol_account(
+    account_info: &AccountInfo,
+    spl_token_program_account_info: &AccountInfo,
+) -> ProgramResult {
+    ensure!(
+        account_info.owner == spl_token_program_account_info.key,
+        ProgramError::IncorrectProgramId
+    );
+    let mint = StateWithExtensions::<Account>::unpack(&account_info.data.borrow())?
+        .base
+        .mint;
+    ensure!(
+        mint == native_mint(spl_token_program_account_info.key),
+        SplStoreError::WrongAccountMint.into()
+    );
+    Ok(())
+}
+
+/// Pay `lamports` from the vault into a wrapped SOL account and sync its token balance
+pub fn wrap_from_vault<'a>(
+    vault_account_info: &AccountInfo<'a>,
+    account_info: &AccountInfo<'a>,
+    spl_token_program_account_info: &AccountInfo<'a>,
+    lamports: Amount,
+) -> ProgramResult {
+    move_lamports(vault_account_info, account_info, lamports)?;
+    let mut sync_ix = sync_native(spl_token_program_account_info.key, account_info.key)?;
+    // The token program ignores the vault, passing it lets the runtime see its debit,
+    // which keeps the lamports of the accounts in the invocation balanced
+    sync_ix
+        .accounts
+        .push(AccountMeta::new(*vault_account_info.key, false));
+    // [writable] The native token account to sync with its underlying lamports.
+    invoke(
+        &sync_ix,
+        &[account_info.clone(), vault_account_info.clone()],
+    )
+}
+
+/// Accounts a client pays wrapped SOL with. The store unwraps the payment through
+/// its native mint ATA, closing it into the vault
+pub struct WrappedSolPayment<'a, 'b> {
+    pub client_account: &'a AccountInfo<'b>,
+    pub store_ata: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> WrappedSolPayment<'a, 'b> {
+    /// - \[writeable] Client wrapped SOL account
+    /// - \[writeable] Store ATA of the native mint
+    /// - [] Native mint account
+    pub fn next<I: Iterator<Item = &'a AccountInfo<'b>>>(
+        store_account_info: &AccountInfo,
+        spl_token_program_account_info: &AccountInfo,
+        accounts_info_iter: &mut I,
+    ) -> Result<Self, ProgramError> {
+        let client_account = next_account_info(accounts_info_iter)?;
+        let store_ata = next_account_info(accounts_info_iter)?;
+        let mint = next_account_info(accounts_info_iter)?;
+
+        ensure!(
+            *mint.key == native_mint(spl_token_program_account_info.key),
+            SplStoreError::WrongAccountMint.into()
+        );
+        ensure!(
+            get_associated_token_address_with_program_id(
+                store_account_info.key,
+                mint.key,
+                spl_token_program_account_info.key,
+            ) == *store_ata.key,
+            SplStoreError::InvalidAtaAddress.into()
+        );
+        ensure!(
+            client_account.is_writable && store_ata.is_writable,
+            SplStoreError::AccountNotWritable.into()
+        );
+        check_wrapped_sol_account(client_account, spl_token_program_account_info)?;
+        Ok(Self {
+            client_account,
+            store_ata,
+            mint,
+        })
+    }
+
+    /// Move `lamports` of wrapped SOL from the client to the store ATA, then close the
+    /// ATA into the vault. The vault receives the lamports the ATA held before as well
+    pub fn charge_client(
+        &self,
+        store_account: &StoreAccount,
+        store_account_info: &AccountInfo<'b>,
+        client_account_info: &AccountInfo<'b>,
+        vault_account_info: &AccountInfo<'b>,
+        spl_token_program_account_info: &AccountInfo<'b>,
+        lamports: Amount,
+    ) -> ProgramResult {
+        let transfer_ix = transfer_checked(
+            spl_token_program_account_info,
+            self.client_account,
+            self.mint,
+            self.store_ata,
+            client_account_info,
+            lamports,
+        )?;
+        // [writable] The source account.
+        // [] The token mint.
+        // [writable] The destination account.
+        // [signer] The source account’s owner/delegate.
+        invoke(
+            &transfer_ix,
+            &[
+                self.client_account.clone(),
+                self.mint.clone(),
+                self.store_ata.clone(),
+                client_account_info.clone(),
+            ],
+        )?;
+
+        let close_ix = close_account(
+            spl_token_program_account_info.key,
+            self.store_ata.key,
+            vault_account_info.key,
+            store_account_info.key,
+            &[],
+        )?;
+        // [writable] The account to close.
+        // [writable] The destination account.
+        // [signer] The account's owner.
+        store_account.invoke_signed(
+            &close_ix,
+            &[
+                self.store_ata.clone(),
+                vault_account_info.clone(),
+                store_account_info.clone(),
+            ],
+        )
+    }
+}
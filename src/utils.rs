@@ -1,10 +1,31 @@
-use spl_associated_token_account::solana_program::account_info::AccountInfo;
-use spl_token::{
-    solana_program::entrypoint::ProgramResult,
-    state::{Account, GenericTokenAccount},
+use spl_associated_token_account::solana_program::{account_info::AccountInfo, program::invoke};
+use spl_token::solana_program::{
+    clock::Clock, entrypoint::ProgramResult, instruction::Instruction, program::invoke_signed,
+    program_error::ProgramError, pubkey::Pubkey, system_instruction, sysvar::Sysvar,
 };
+use spl_token_2022::{
+    extension::{
+        transfer_fee::{TransferFeeAmount, TransferFeeConfig},
+        BaseStateWithExtensions, StateWithExtensions,
+    },
+    generic_token_account::GenericTokenAccount,
+    state::{Account, Mint},
+};
+
+use crate::{ensure, error::SplStoreError};
 
-use crate::error::SplStoreError;
+/// Check that the token program is SPL Token or Token-2022 and owns the mint
+pub fn check_token_program(token_program: &AccountInfo, token_mint: &AccountInfo) -> ProgramResult {
+    ensure!(
+        spl_token::check_id(token_program.key) || spl_token_2022::check_id(token_program.key),
+        ProgramError::IncorrectProgramId
+    );
+    ensure!(
+        token_mint.owner == token_program.key,
+        ProgramError::IncorrectProgramId
+    );
+    Ok(())
+}
 
 pub fn check_ata_mint(ata_info: &AccountInfo, token_mint: &AccountInfo) -> ProgramResult {
     match Account::unpack_account_mint(&ata_info.data.borrow()) {
@@ -15,3 +36,158 @@ pub fn check_ata_mint(ata_info: &AccountInfo, token_mint: &AccountInfo) -> Progr
         _ => Ok(()),
     }
 }
+
+/// Number of decimals of the mint, which relates its base units to whole tokens
+pub fn mint_decimals(token_mint: &AccountInfo) -> Result<u8, ProgramError> {
+    Ok(
+        StateWithExtensions::<Mint>::unpack(&token_mint.data.borrow())?
+            .base
+            .decimals,
+    )
+}
+
+/// Amount to transfer for the destination to receive `amount`, covering the transfer fee
+/// the mint charges in the current epoch
+pub fn pre_fee_amount(token_mint: &AccountInfo, amount: u64) -> Result<u64, ProgramError> {
+    let mint_data = token_mint.data.borrow();
+    let mint = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+    let Ok(transfer_fee_config) = mint.get_extension::<TransferFeeConfig>() else {
+        return Ok(amount);
+    };
+    let fee = transfer_fee_config
+        .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(SplStoreError::MathOverflow)?;
+    amount
+        .checked_add(fee)
+        .ok_or(SplStoreError::MathOverflow.into())
+}
+
+/// Token balance of an account of either token program
+pub fn token_amount(token_account: &AccountInfo) -> Result<u64, ProgramError> {
+    Ok(
+        StateWithExtensions::<Account>::unpack(&token_account.data.borrow())?
+            .base
+            .amount,
+    )
+}
+
+/// Transfer fees withheld in a token account, which have to be harvested before it can be closed
+pub fn withheld_amount(token_account: &AccountInfo) -> Result<u64, ProgramError> {
+    let account_data = token_account.data.borrow();
+    let account = StateWithExtensions::<Account>::unpack(&account_data)?;
+    Ok(account
+        .get_extension::<TransferFeeAmount>()
+        .map_or(0, |transfer_fee_amount| {
+            transfer_fee_amount.withheld_amount.into()
+        }))
+}
+
+/// `TransferChecked` instruction for the token program owning the mint.
+/// The destination receives less than `amount` if the mint charges a transfer fee
+/// - \[writeable] Source account
+/// - [] Token Mint account
+/// - \[writeable] Destination account
+/// - [signer] Source account owner/delegate
+pub fn transfer_checked(
+    token_program: &AccountInfo,
+    source: &AccountInfo,
+    token_mint: &AccountInfo,
+    destination: &AccountInfo,
+    authority: &AccountInfo,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    spl_token_2022::instruction::transfer_checked(
+        token_program.key,
+        source.key,
+        token_mint.key,
+        destination.key,
+        authority.key,
+        &[],
+        amount,
+        mint_decimals(token_mint)?,
+    )
+}
+
+/// Move lamports between accounts the program may debit and credit directly
+pub fn move_lamports(
+    source: &AccountInfo,
+    destination: &AccountInfo,
+    lamports: u64,
+) -> ProgramResult {
+    let source_lamports = source
+        .lamports()
+        .checked_sub(lamports)
+        .ok_or(SplStoreError::InsufficientFundsForTransaction)?;
+    let destination_lamports = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(SplStoreError::MathOverflow)?;
+    **source.try_borrow_mut_lamports()? = source_lamports;
+    **destination.try_borrow_mut_lamports()? = destination_lamports;
+    Ok(())
+}
+
+/// Create a program derived account, also when someone already sent lamports to its address
+/// - \[writeable, signer] Funding account
+/// - \[writeable] New account
+/// - [] System program account
+pub fn create_pda_account(
+    account_infos: &[AccountInfo],
+    lamports: u64,
+    space: usize,
+    owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let [funding_account_info, new_account_info, system_program_account_info] = account_infos
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if new_account_info.lamports() == 0 {
+        // [WRITE, SIGNER] Funding account
+        // [WRITE, SIGNER] New account
+        return invoke_signed(
+            &system_instruction::create_account(
+                funding_account_info.key,
+                new_account_info.key,
+                lamports,
+                space as u64,
+                owner,
+            ),
+            &[
+                funding_account_info.clone(),
+                new_account_info.clone(),
+                system_program_account_info.clone(),
+            ],
+            &[signer_seeds],
+        );
+    }
+
+    let top_up = lamports.saturating_sub(new_account_info.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(funding_account_info.key, new_account_info.key, top_up),
+            &[
+                funding_account_info.clone(),
+                new_account_info.clone(),
+                system_program_account_info.clone(),
+            ],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(new_account_info.key, space as u64),
+        &[
+            new_account_info.clone(),
+            system_program_account_info.clone(),
+        ],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(new_account_info.key, owner),
+        &[
+            new_account_info.clone(),
+            system_program_account_info.clone(),
+        ],
+        &[signer_seeds],
+    )
+}
@@ -0,0 +1,191 @@
+use solana_sdk::program_error::ProgramError;
+use spl_store::{
+    error::SplStoreError,
+    store::{
+        curve::Curve,
+        pricing::{
+            buy_lamports, fee, lamports, reserves_buy_lamports, reserves_sell_lamports,
+            sell_lamports, Rounding,
+        },
+    },
+};
+
+fn overflow() -> Result<u64, ProgramError> {
+    Err(SplStoreError::MathOverflow.into())
+}
+
+#[test]
+fn whole_tokens_need_no_rounding() {
+    assert_eq!(buy_lamports(7, 37, 0), Ok(259));
+    assert_eq!(sell_lamports(7, 37, 0), Ok(259));
+    assert_eq!(buy_lamports(3_500_000, 2_500_000, 6), Ok(8_750_000));
+    assert_eq!(sell_lamports(3_500_000, 2_500_000, 6), Ok(8_750_000));
+}
+
+#[test]
+fn fractions_are_rounded_in_favour_of_the_store() {
+    // 1 base unit of a 9 decimal mint at 1 lamport per token is a billionth of a lamport
+    assert_eq!(buy_lamports(1, 1, 9), Ok(0));
+    assert_eq!(sell_lamports(1, 1, 9), Ok(1));
+    // 1.5 lamports
+    assert_eq!(buy_lamports(3, 5, 1), Ok(1));
+    assert_eq!(sell_lamports(3, 5, 1), Ok(2));
+    // Just below and at a whole lamport
+    assert_eq!(buy_lamports(999_999_999, 1, 9), Ok(0));
+    assert_eq!(sell_lamports(999_999_999, 1, 9), Ok(1));
+    assert_eq!(buy_lamports(1_000_000_000, 1, 9), Ok(1));
+    assert_eq!(sell_lamports(1_000_000_000, 1, 9), Ok(1));
+    assert_eq!(sell_lamports(1_000_000_001, 1, 9), Ok(2));
+}
+
+#[test]
+fn zero_amount_or_price_is_free() {
+    for rounding in [Rounding::Down, Rounding::Up] {
+        assert_eq!(lamports(0, u64::MAX, 0, rounding), Ok(0));
+        assert_eq!(lamports(u64::MAX, 0, 0, rounding), Ok(0));
+        assert_eq!(lamports(0, 0, 255, rounding), Ok(0));
+    }
+}
+
+#[test]
+fn largest_representable_values() {
+    assert_eq!(buy_lamports(u64::MAX, 1, 0), Ok(u64::MAX));
+    assert_eq!(sell_lamports(1, u64::MAX, 0), Ok(u64::MAX));
+    // u64::MAX * u64::MAX / 10^20 still fits
+    assert_eq!(
+        buy_lamports(u64::MAX, u64::MAX, 20),
+        Ok(3_402_823_669_209_384_634)
+    );
+    assert_eq!(
+        sell_lamports(u64::MAX, u64::MAX, 20),
+        Ok(3_402_823_669_209_384_635)
+    );
+}
+
+#[test]
+fn results_beyond_u64_overflow() {
+    assert_eq!(buy_lamports(u64::MAX, 2, 0), overflow());
+    assert_eq!(sell_lamports(u64::MAX, u64::MAX, 0), overflow());
+    assert_eq!(buy_lamports(u64::MAX, u64::MAX, 19), overflow());
+    // 155 * 1_190_112_520_884_487_201 / 10 is u64::MAX + 0.5, only rounding up overflows
+    assert_eq!(
+        buy_lamports(155, 1_190_112_520_884_487_201, 1),
+        Ok(u64::MAX)
+    );
+    assert_eq!(sell_lamports(155, 1_190_112_520_884_487_201, 1), overflow());
+}
+
+#[test]
+fn scales_beyond_u128_round_to_zero_or_one() {
+    assert_eq!(buy_lamports(u64::MAX, u64::MAX, 38), Ok(3));
+    assert_eq!(buy_lamports(u64::MAX, u64::MAX, 39), Ok(0));
+    assert_eq!(sell_lamports(u64::MAX, u64::MAX, 39), Ok(1));
+    assert_eq!(buy_lamports(1, 1, u8::MAX), Ok(0));
+    assert_eq!(sell_lamports(1, 1, u8::MAX), Ok(1));
+}
+
+#[test]
+fn fees_round_up() {
+    assert_eq!(fee(2_000, 100), Ok(20));
+    assert_eq!(fee(2_001, 100), Ok(21));
+    assert_eq!(fee(1, 1), Ok(1));
+    assert_eq!(fee(0, 10_000), Ok(0));
+    assert_eq!(fee(u64::MAX, 10_000), Ok(u64::MAX));
+    assert_eq!(fee(u64::MAX, 0), Ok(0));
+}
+
+fn linear(base_price: u64, price_step: u64, step_interval: u64) -> Curve {
+    Curve::Linear {
+        base_price,
+        price_step,
+        step_interval,
+    }
+}
+
+fn exponential(base_price: u64, growth_bps: u16, step_interval: u64) -> Curve {
+    Curve::Exponential {
+        base_price,
+        growth_bps,
+        step_interval,
+    }
+}
+
+#[test]
+fn curves_are_integrated_over_the_traded_amount() {
+    // 10 + x lamports per token after selling x tokens
+    let curve = linear(10, 1, 1);
+    assert_eq!(curve.sell_lamports(0, 2, 0), Ok(22));
+    assert_eq!(curve.buy_lamports(2, 2, 0), Ok(22));
+    assert_eq!(curve.sell_lamports(4, 2, 0), Ok(30));
+    // 10.5 lamports
+    assert_eq!(curve.sell_lamports(0, 1, 0), Ok(11));
+    assert_eq!(curve.buy_lamports(1, 1, 0), Ok(10));
+    // 10 + x / 100 lamports per token of 100 base units, 10.5 and 11.5 lamports
+    let curve = linear(10, 1, 100);
+    assert_eq!(curve.sell_lamports(0, 100, 2), Ok(11));
+    assert_eq!(curve.buy_lamports(200, 100, 2), Ok(11));
+
+    // 10% more per token, 10000, 11000 and 12100 lamports
+    let curve = exponential(10_000, 1_000, 1);
+    assert_eq!(curve.sell_lamports(0, 3, 0), Ok(33_100));
+    assert_eq!(curve.buy_lamports(3, 1, 0), Ok(12_100));
+    // The price is constant within a step
+    let curve = exponential(10_000, 1_000, 2);
+    assert_eq!(curve.sell_lamports(0, 1, 0), Ok(10_000));
+    assert_eq!(curve.sell_lamports(0, 3, 0), Ok(31_000));
+    assert_eq!(curve.buy_lamports(4, 1, 0), Ok(11_000));
+}
+
+#[test]
+fn curves_round_trips_never_pay_the_client() {
+    for curve in [linear(3, 7, 11), exponential(1_000_003, 137, 5)] {
+        for sold in [0, 1, 4, 10, 99] {
+            for amount in [1, 2, 5, 13] {
+                let paid = curve.sell_lamports(sold, amount, 1).unwrap();
+                let refunded = curve.buy_lamports(sold + amount, amount, 1).unwrap();
+                assert!(refunded <= paid);
+                assert!(paid - refunded <= 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn curves_reject_invalid_trades() {
+    assert_eq!(
+        linear(10, 1, 1).buy_lamports(1, 2, 0),
+        Err(SplStoreError::CurveSupplyExceeded.into())
+    );
+    assert_eq!(
+        linear(1, u64::MAX, 1).sell_lamports(0, u64::MAX, 0),
+        overflow()
+    );
+    assert_eq!(
+        exponential(1, 10_000, 1).sell_lamports(0, 200, 0),
+        overflow()
+    );
+    let invalid = || Err(SplStoreError::InvalidCurve.into());
+    assert_eq!(linear(1, 1, 0).validate(), invalid());
+    assert_eq!(exponential(1, 0, 1).validate(), invalid());
+    assert_eq!(exponential(1, 1, 0).validate(), invalid());
+    assert_eq!(linear(1, 0, 1).validate(), Ok(()));
+}
+
+#[test]
+fn reserves_keep_their_product() {
+    // 111.1 lamports for 10 of 100 tokens against 1000 lamports
+    assert_eq!(reserves_sell_lamports(1_000, 100, 10), Ok(112));
+    // 111.2 lamports for the 10 tokens back
+    assert_eq!(reserves_buy_lamports(1_112, 90, 10), Ok(111));
+    assert_eq!(
+        reserves_buy_lamports(u64::MAX, u64::MAX, u64::MAX),
+        Ok(u64::MAX / 2)
+    );
+    assert_eq!(reserves_sell_lamports(u64::MAX, 3, 2), overflow());
+
+    let insufficient = || Err(SplStoreError::InsufficientReserves.into());
+    assert_eq!(reserves_sell_lamports(1_000, 100, 100), insufficient());
+    assert_eq!(reserves_sell_lamports(0, 100, 10), insufficient());
+    assert_eq!(reserves_buy_lamports(1_000, 0, 10), insufficient());
+    assert_eq!(reserves_buy_lamports(0, 100, 10), insufficient());
+}
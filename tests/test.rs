@@ -1,30 +1,51 @@
-use borsh::BorshDeserialize;
+use borsh::{BorshDeserialize, BorshSerialize};
 use eyre::eyre;
-use solana_program_test::{processor, tokio, BanksClient, ProgramTest};
+use solana_program_test::{processor, tokio, BanksClient, BanksClientError, ProgramTest};
 use solana_sdk::{
+    clock::Clock,
+    ed25519_program,
     hash::Hash,
-    instruction::{AccountMeta, Instruction},
+    instruction::{AccountMeta, Instruction, InstructionError},
     message::Message,
+    native_token::LAMPORTS_PER_SOL,
     program_pack::Pack,
     pubkey::Pubkey,
+    rent::Rent,
     signature::Keypair,
     signer::Signer,
-    system_program,
-    transaction::Transaction,
+    system_program, sysvar,
+    transaction::{Transaction, TransactionError},
 };
 use spl_associated_token_account::{
-    get_associated_token_address, instruction::create_associated_token_account,
+    get_associated_token_address, get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account,
 };
 use spl_token::{
     instruction::initialize_mint,
     state::{Account, Mint},
     ui_amount_to_amount,
 };
+use spl_token_2022::{
+    extension::{
+        transfer_fee::instruction::initialize_transfer_fee_config, ExtensionType,
+        StateWithExtensions,
+    },
+    state::{Account as Account2022, Mint as Mint2022},
+};
 
 use spl_store::{
     entrypoint::process_instruction,
+    error::SplStoreError,
     instruction::SplStoreInstruction,
-    store::{account::StoreAccount, Price},
+    store::{
+        account::StoreAccount,
+        curve::{Curve, PricingMode},
+        multisig::Multisig,
+        prices::Prices,
+        quote::{Quote, QuoteMessage, QuoteSide, QUOTE_NONCE_WINDOW},
+        role::Role,
+        Price,
+    },
 };
 
 async fn create_token_mint(
@@ -118,10 +139,872 @@ async fn fetch_account_info_data<T: BorshDeserialize>(
         .get_account(pubkey)
         .await?
         .ok_or(eyre!("get_account"))?;
-    let account_data = T::try_from_slice(&account.data)?;
+    let account_data = T::deserialize(&mut account.data.as_slice())?;
     Ok(account_data)
 }
 
+/// Prices without a spread
+fn flat_prices(price: Price) -> Prices {
+    Prices {
+        bid: price,
+        ask: price,
+    }
+}
+
+fn update_price(price: Price) -> SplStoreInstruction {
+    SplStoreInstruction::UpdatePrice {
+        bid: Some(price),
+        ask: Some(price),
+    }
+}
+
+/// Ed25519 program instruction verifying `signer`'s signature of `message`,
+/// with all data inside the instruction
+fn ed25519_instruction(signer: &Keypair, message: &[u8]) -> Instruction {
+    const PUBLIC_KEY_OFFSET: u16 = 16;
+    const SIGNATURE_OFFSET: u16 = PUBLIC_KEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+    let signature = signer.sign_message(message);
+    let mut data = vec![1, 0];
+    for offset in [
+        SIGNATURE_OFFSET,
+        u16::MAX,
+        PUBLIC_KEY_OFFSET,
+        u16::MAX,
+        MESSAGE_OFFSET,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+    Instruction::new_with_bytes(ed25519_program::id(), &data, vec![])
+}
+
+fn assert_store_error(result: Result<(), BanksClientError>, expected: SplStoreError) {
+    assert_instruction_error(result, 0, expected);
+}
+
+fn assert_instruction_error(
+    result: Result<(), BanksClientError>,
+    index: u8,
+    expected: SplStoreError,
+) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(index, InstructionError::Custom(expected as u32))
+    );
+}
+
+struct StoreFixture {
+    banks_client: BanksClient,
+    payer: Keypair,
+    program_id: Pubkey,
+    store: Pubkey,
+    token_mint: Keypair,
+    /// Token program owning `token_mint`
+    token_program: Pubkey,
+    client: Keypair,
+}
+
+impl StoreFixture {
+    async fn new() -> Self {
+        dotenv::dotenv().ok();
+
+        let program_id = Pubkey::new_unique();
+        let client = Keypair::new();
+        let mut program_test =
+            ProgramTest::new("spl-store", program_id, processor!(process_instruction));
+        program_test.add_account(
+            client.pubkey(),
+            solana_sdk::account::Account {
+                lamports: 69_000_000_000,
+                owner: system_program::id(),
+                ..Default::default()
+            },
+        );
+        let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+        // Without decimals prices are simply lamports per base unit
+        let token_mint = Keypair::new();
+        create_token_mint(
+            &mut banks_client,
+            recent_blockhash,
+            &payer,
+            &token_mint,
+            &spl_token::id(),
+            &payer.pubkey(),
+            ui_amount_to_amount(9_000f64, 9),
+            0,
+        )
+        .await
+        .unwrap();
+
+        Self {
+            banks_client,
+            payer,
+            program_id,
+            store: Pubkey::default(),
+            token_mint,
+            token_program: spl_token::id(),
+            client,
+        }
+    }
+
+    /// Fixture trading a Token-2022 mint without decimals that charges a transfer fee
+    async fn new_token_2022(transfer_fee_basis_points: u16) -> Self {
+        let mut fixture = Self::new().await;
+        fixture.token_mint = fixture.create_mint_2022(transfer_fee_basis_points, 0).await;
+        fixture.token_program = spl_token_2022::id();
+        fixture
+    }
+
+    /// Create a Token-2022 mint charging a transfer fee, the payer is its mint authority
+    async fn create_mint_2022(&mut self, transfer_fee_basis_points: u16, decimals: u8) -> Keypair {
+        let token_mint = Keypair::new();
+        let space = ExtensionType::try_calculate_account_len::<Mint2022>(&[
+            ExtensionType::TransferFeeConfig,
+        ])
+        .unwrap();
+        let rent = self.banks_client.get_rent().await.unwrap();
+        let instructions = [
+            solana_sdk::system_instruction::create_account(
+                &self.payer.pubkey(),
+                &token_mint.pubkey(),
+                rent.minimum_balance(space),
+                space as u64,
+                &spl_token_2022::id(),
+            ),
+            initialize_transfer_fee_config(
+                &spl_token_2022::id(),
+                &token_mint.pubkey(),
+                None,
+                None,
+                transfer_fee_basis_points,
+                u64::MAX,
+            )
+            .unwrap(),
+            spl_token_2022::instruction::initialize_mint(
+                &spl_token_2022::id(),
+                &token_mint.pubkey(),
+                &self.payer.pubkey(),
+                None,
+                decimals,
+            )
+            .unwrap(),
+        ];
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer, &token_mint],
+            self.banks_client.get_latest_blockhash().await.unwrap(),
+        );
+        self.banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+        token_mint
+    }
+
+    /// Token program owning `mint`
+    async fn token_program_of(&mut self, mint: &Pubkey) -> Pubkey {
+        self.banks_client
+            .get_account(*mint)
+            .await
+            .unwrap()
+            .unwrap()
+            .owner
+    }
+
+    /// ATA of `owner` for the traded mint
+    fn token_ata(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(
+            owner,
+            &self.token_mint.pubkey(),
+            &self.token_program,
+        )
+    }
+
+    fn store_ata(&self) -> Pubkey {
+        self.token_ata(&self.store)
+    }
+
+    fn vault(&self) -> Pubkey {
+        StoreAccount::find_vault_address(&self.program_id, &self.store).0
+    }
+
+    fn admin_ata(&self, admin: &Pubkey) -> Pubkey {
+        self.token_ata(admin)
+    }
+
+    fn client_ata(&self) -> Pubkey {
+        self.token_ata(&self.client.pubkey())
+    }
+
+    async fn create_client_ata(&mut self) {
+        let instruction = create_associated_token_account(
+            &self.payer.pubkey(),
+            &self.client.pubkey(),
+            &self.token_mint.pubkey(),
+            &self.token_program,
+        );
+        self.process(instruction, &[]).await.unwrap();
+    }
+
+    async fn mint_to(&mut self, account: &Pubkey, amount: u64) {
+        let mint = self.token_mint.pubkey();
+        self.mint_tokens_to(&mint, account, amount).await;
+    }
+
+    async fn mint_tokens_to(&mut self, mint: &Pubkey, account: &Pubkey, amount: u64) {
+        let token_program = self.token_program_of(mint).await;
+        let instruction = spl_token_2022::instruction::mint_to(
+            &token_program,
+            mint,
+            account,
+            &self.payer.pubkey(),
+            &[],
+            amount,
+        )
+        .unwrap();
+        self.process(instruction, &[]).await.unwrap();
+    }
+
+    async fn token_balance(&mut self, account: Pubkey) -> u64 {
+        let account = self
+            .banks_client
+            .get_account(account)
+            .await
+            .unwrap()
+            .unwrap();
+        StateWithExtensions::<Account2022>::unpack(&account.data)
+            .unwrap()
+            .base
+            .amount
+    }
+
+    async fn buy(&mut self, amount: u64) -> Result<(), BanksClientError> {
+        self.trade(SplStoreInstruction::Buy {
+            amount,
+            min_lamports: 0,
+        })
+        .await
+    }
+
+    async fn sell(&mut self, amount: u64) -> Result<(), BanksClientError> {
+        self.trade(SplStoreInstruction::Sell {
+            amount,
+            max_lamports: u64::MAX,
+        })
+        .await
+    }
+
+    fn quote_message(&self, side: QuoteSide, quote: Quote) -> QuoteMessage {
+        QuoteMessage {
+            side,
+            store: self.store,
+            mint: self.token_mint.pubkey(),
+            client: self.client.pubkey(),
+            fee_bps: 0,
+            quote,
+        }
+    }
+
+    /// Send a `BuyWithQuote` or `SellWithQuote` signed by the client, preceded by
+    /// the verification of `quote_signer`'s signature over `signed`
+    async fn trade_with_quote(
+        &mut self,
+        instruction: SplStoreInstruction,
+        signed: &QuoteMessage,
+        quote_signer: &Keypair,
+    ) -> Result<(), BanksClientError> {
+        let instructions = [
+            ed25519_instruction(quote_signer, &signed.try_to_vec().unwrap()),
+            Instruction::new_with_borsh(
+                self.program_id,
+                &instruction,
+                vec![
+                    AccountMeta::new(self.payer.pubkey(), true),
+                    AccountMeta::new(self.store, false),
+                    AccountMeta::new(self.vault(), false),
+                    AccountMeta::new(self.store_ata(), false),
+                    AccountMeta::new(self.client.pubkey(), true),
+                    AccountMeta::new(self.client_ata(), false),
+                    AccountMeta::new(self.token_mint.pubkey(), false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new_readonly(self.token_program, false),
+                    AccountMeta::new_readonly(sysvar::instructions::id(), false),
+                ],
+            ),
+        ];
+        let recent_blockhash = self.banks_client.get_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer, &self.client],
+            recent_blockhash,
+        );
+        self.banks_client.process_transaction(transaction).await
+    }
+
+    async fn slot(&mut self) -> u64 {
+        self.banks_client.get_sysvar::<Clock>().await.unwrap().slot
+    }
+
+    /// Send a `Buy` or `Sell` signed by the client
+    async fn trade(&mut self, instruction: SplStoreInstruction) -> Result<(), BanksClientError> {
+        self.trade_with_accounts(instruction, vec![]).await
+    }
+
+    /// Send a `Buy` or `Sell` settled in `quote_mint`
+    async fn trade_in_quote_mint(
+        &mut self,
+        instruction: SplStoreInstruction,
+        quote_mint: &Pubkey,
+    ) -> Result<(), BanksClientError> {
+        self.trade_in_quote_mint_with(instruction, quote_mint, vec![])
+            .await
+    }
+
+    /// Like `trade_in_quote_mint`, with `extra_accounts` after the ATA program
+    async fn trade_in_quote_mint_with(
+        &mut self,
+        instruction: SplStoreInstruction,
+        quote_mint: &Pubkey,
+        extra_accounts: Vec<AccountMeta>,
+    ) -> Result<(), BanksClientError> {
+        let quote_token_program = self.token_program_of(quote_mint).await;
+        let quote_ata = |owner| {
+            get_associated_token_address_with_program_id(owner, quote_mint, &quote_token_program)
+        };
+        let quote_accounts = vec![
+            AccountMeta::new(quote_ata(&self.store), false),
+            AccountMeta::new(quote_ata(&self.client.pubkey()), false),
+            AccountMeta::new_readonly(*quote_mint, false),
+            AccountMeta::new_readonly(quote_token_program, false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        ];
+        let accounts = quote_accounts.into_iter().chain(extra_accounts).collect();
+        self.trade_with_accounts(instruction, accounts).await
+    }
+
+    async fn trade_with_accounts(
+        &mut self,
+        instruction: SplStoreInstruction,
+        extra_accounts: Vec<AccountMeta>,
+    ) -> Result<(), BanksClientError> {
+        let mut accounts = vec![
+            AccountMeta::new(self.payer.pubkey(), true),
+            AccountMeta::new(self.store, false),
+            AccountMeta::new(self.vault(), false),
+            AccountMeta::new(self.store_ata(), false),
+            AccountMeta::new(self.client.pubkey(), true),
+            AccountMeta::new(self.client_ata(), false),
+            AccountMeta::new(self.token_mint.pubkey(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(self.token_program, false),
+        ];
+        accounts.extend(extra_accounts);
+        let instruction = Instruction::new_with_borsh(self.program_id, &instruction, accounts);
+        let recent_blockhash = self.banks_client.get_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.payer.pubkey()),
+            &[&self.payer, &self.client],
+            recent_blockhash,
+        );
+        self.banks_client.process_transaction(transaction).await
+    }
+
+    async fn create_ata(&mut self, owner: &Pubkey) -> Pubkey {
+        let instruction = create_associated_token_account(
+            &self.payer.pubkey(),
+            owner,
+            &self.token_mint.pubkey(),
+            &self.token_program,
+        );
+        self.process(instruction, &[]).await.unwrap();
+        self.token_ata(owner)
+    }
+
+    async fn close_store(
+        &mut self,
+        admin: &Keypair,
+        token_destination: &Pubkey,
+        lamports_destination: &Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let store_ata = self.store_ata();
+        self.close_store_with_accounts(
+            admin,
+            &store_ata,
+            vec![],
+            token_destination,
+            lamports_destination,
+        )
+        .await
+    }
+
+    /// Close the store, passing `quote_accounts` after the token program
+    async fn close_store_with_accounts(
+        &mut self,
+        admin: &Keypair,
+        store_ata: &Pubkey,
+        quote_accounts: Vec<AccountMeta>,
+        token_destination: &Pubkey,
+        lamports_destination: &Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let mut accounts = vec![
+            AccountMeta::new(self.store, false),
+            AccountMeta::new(self.vault(), false),
+            AccountMeta::new(*store_ata, false),
+            AccountMeta::new(*token_destination, false),
+            AccountMeta::new(*lamports_destination, false),
+            AccountMeta::new(self.token_mint.pubkey(), false),
+            AccountMeta::new_readonly(self.token_program, false),
+        ];
+        accounts.extend(quote_accounts);
+        accounts.push(AccountMeta::new_readonly(admin.pubkey(), true));
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &SplStoreInstruction::CloseStore,
+            accounts,
+        );
+        self.process(instruction, &[admin]).await
+    }
+
+    async fn deposit_sol(&mut self, amount: u64) -> Result<(), BanksClientError> {
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &SplStoreInstruction::DepositSol(amount),
+            vec![
+                AccountMeta::new(self.payer.pubkey(), true),
+                AccountMeta::new_readonly(self.store, false),
+                AccountMeta::new(self.vault(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        self.process(instruction, &[]).await
+    }
+
+    async fn withdraw_sol(
+        &mut self,
+        amount: u64,
+        destination: &Pubkey,
+        authority: &Keypair,
+    ) -> Result<(), BanksClientError> {
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &SplStoreInstruction::WithdrawSol(amount),
+            vec![
+                AccountMeta::new_readonly(self.store, false),
+                AccountMeta::new(self.vault(), false),
+                AccountMeta::new(*destination, false),
+                AccountMeta::new_readonly(authority.pubkey(), true),
+            ],
+        );
+        self.process(instruction, &[authority]).await
+    }
+
+    async fn collect_fees(&mut self, fee_recipient: &Pubkey) -> Result<(), BanksClientError> {
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &SplStoreInstruction::CollectFees,
+            vec![
+                AccountMeta::new(self.store, false),
+                AccountMeta::new(self.vault(), false),
+                AccountMeta::new(*fee_recipient, false),
+            ],
+        );
+        self.process(instruction, &[]).await
+    }
+
+    async fn balance(&mut self, account: Pubkey) -> u64 {
+        self.banks_client.get_balance(account).await.unwrap()
+    }
+
+    /// Initialize a store with inventory and a client holding tokens
+    async fn initialize_trading(&mut self, price: Price, admin: &Keypair) {
+        self.initialize(price, admin).await.unwrap();
+        self.create_client_ata().await;
+        let client_ata = self.client_ata();
+        self.mint_to(&client_ata, 100).await;
+
+        let admin_ata = self.create_ata(&admin.pubkey()).await;
+        self.mint_to(&admin_ata, 100).await;
+        self.deposit_tokens(100, &admin_ata, admin, admin)
+            .await
+            .unwrap();
+    }
+
+    async fn deposit_tokens(
+        &mut self,
+        amount: u64,
+        source: &Pubkey,
+        source_owner: &Keypair,
+        authority: &Keypair,
+    ) -> Result<(), BanksClientError> {
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &SplStoreInstruction::DepositTokens(amount),
+            vec![
+                AccountMeta::new_readonly(self.store, false),
+                AccountMeta::new(self.store_ata(), false),
+                AccountMeta::new(*source, false),
+                AccountMeta::new_readonly(source_owner.pubkey(), true),
+                AccountMeta::new_readonly(self.token_mint.pubkey(), false),
+                AccountMeta::new_readonly(self.token_program, false),
+                AccountMeta::new_readonly(authority.pubkey(), true),
+            ],
+        );
+        self.process(instruction, &[source_owner, authority]).await
+    }
+
+    async fn withdraw_tokens(
+        &mut self,
+        amount: u64,
+        destination: &Pubkey,
+        authority: &Keypair,
+    ) -> Result<(), BanksClientError> {
+        let instruction =
+            self.withdraw_tokens_instruction(amount, destination, &authority.pubkey());
+        self.process(instruction, &[authority]).await
+    }
+
+    fn withdraw_tokens_instruction(
+        &self,
+        amount: u64,
+        destination: &Pubkey,
+        authority: &Pubkey,
+    ) -> Instruction {
+        Instruction::new_with_borsh(
+            self.program_id,
+            &SplStoreInstruction::WithdrawTokens(amount),
+            vec![
+                AccountMeta::new_readonly(self.store, false),
+                AccountMeta::new(self.store_ata(), false),
+                AccountMeta::new(*destination, false),
+                AccountMeta::new_readonly(self.token_mint.pubkey(), false),
+                AccountMeta::new_readonly(self.token_program, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+        )
+    }
+
+    async fn create_mint(&mut self, decimals: u8) -> Keypair {
+        let mint = Keypair::new();
+        let recent_blockhash = self.banks_client.get_latest_blockhash().await.unwrap();
+        create_token_mint(
+            &mut self.banks_client,
+            recent_blockhash,
+            &self.payer,
+            &mint,
+            &spl_token::id(),
+            &self.payer.pubkey(),
+            ui_amount_to_amount(9_000f64, 9),
+            decimals,
+        )
+        .await
+        .unwrap();
+        mint
+    }
+
+    async fn add_catalog_entry(
+        &mut self,
+        price: Price,
+        mint: &Pubkey,
+        admin: &Keypair,
+    ) -> Result<(), BanksClientError> {
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &SplStoreInstruction::AddCatalogEntry(flat_prices(price)),
+            vec![
+                AccountMeta::new(self.store, false),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new_readonly(admin.pubkey(), true),
+            ],
+        );
+        self.process(instruction, &[admin]).await
+    }
+
+    /// Delist the fixture's token mint, passing the store ATA holding its inventory
+    fn remove_catalog_entry(&self, admin: &Pubkey) -> Instruction {
+        Instruction::new_with_borsh(
+            self.program_id,
+            &SplStoreInstruction::RemoveCatalogEntry(self.token_mint.pubkey()),
+            vec![
+                AccountMeta::new(self.store, false),
+                AccountMeta::new_readonly(self.store_ata(), false),
+                AccountMeta::new_readonly(self.token_mint.pubkey(), false),
+                AccountMeta::new_readonly(self.token_program, false),
+                AccountMeta::new_readonly(*admin, true),
+            ],
+        )
+    }
+
+    async fn initialize(&mut self, price: Price, admin: &Keypair) -> Result<(), BanksClientError> {
+        self.initialize_indexed(price, admin, 0).await
+    }
+
+    async fn initialize_indexed(
+        &mut self,
+        price: Price,
+        admin: &Keypair,
+        index: u64,
+    ) -> Result<(), BanksClientError> {
+        self.initialize_as(price, &admin.pubkey(), index, &[admin])
+            .await
+    }
+
+    /// Initialize the store of `admin`, a multisig admin approves with `signers`
+    async fn initialize_as(
+        &mut self,
+        price: Price,
+        admin: &Pubkey,
+        index: u64,
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        self.store =
+            StoreAccount::find_address(&self.program_id, admin, &self.token_mint.pubkey(), index).0;
+        self.initialize_at(self.store, price, admin, index, signers)
+            .await
+    }
+
+    async fn initialize_at(
+        &mut self,
+        store: Pubkey,
+        price: Price,
+        admin: &Pubkey,
+        index: u64,
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let mut accounts = vec![
+            AccountMeta::new(self.payer.pubkey(), true),
+            AccountMeta::new(self.token_ata(&store), false),
+            AccountMeta::new(store, false),
+            AccountMeta::new_readonly(self.token_mint.pubkey(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(self.token_program, false),
+            AccountMeta::new_readonly(
+                *admin,
+                signers.iter().any(|signer| signer.pubkey() == *admin),
+            ),
+            AccountMeta::new(
+                StoreAccount::find_vault_address(&self.program_id, &store).0,
+                false,
+            ),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        ];
+        accounts.extend(
+            signers
+                .iter()
+                .filter(|signer| signer.pubkey() != *admin)
+                .map(|signer| AccountMeta::new_readonly(signer.pubkey(), true)),
+        );
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &SplStoreInstruction::Initialize(flat_prices(price), 10_000_000_000, index),
+            accounts,
+        );
+        self.process(instruction, signers).await
+    }
+
+    async fn process(
+        &mut self,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            self.banks_client.get_latest_blockhash().await.unwrap(),
+        );
+        self.banks_client.process_transaction(transaction).await
+    }
+
+    /// Send an instruction taking the store and a single authority account,
+    /// the authority signs only if its keypair is among `signers`
+    async fn process_as(
+        &mut self,
+        instruction: SplStoreInstruction,
+        authority: &Pubkey,
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &instruction,
+            vec![
+                AccountMeta::new(self.store, false),
+                AccountMeta::new_readonly(
+                    *authority,
+                    signers.iter().any(|signer| signer.pubkey() == *authority),
+                ),
+            ],
+        );
+        self.process(instruction, signers).await
+    }
+
+    /// Like `process_as`, with `signers` passed as multisig signer accounts
+    async fn process_as_multisig(
+        &mut self,
+        instruction: SplStoreInstruction,
+        multisig: &Pubkey,
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let mut accounts = vec![
+            AccountMeta::new(self.store, false),
+            AccountMeta::new_readonly(*multisig, false),
+        ];
+        accounts.extend(
+            signers
+                .iter()
+                .map(|signer| AccountMeta::new_readonly(signer.pubkey(), true)),
+        );
+        let instruction = Instruction::new_with_borsh(self.program_id, &instruction, accounts);
+        self.process(instruction, signers).await
+    }
+
+    async fn create_multisig(
+        &mut self,
+        m: u8,
+        signers: &[Pubkey],
+    ) -> Result<Pubkey, BanksClientError> {
+        let multisig = Keypair::new();
+        let mut accounts = vec![
+            AccountMeta::new(self.payer.pubkey(), true),
+            AccountMeta::new(multisig.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.extend(
+            signers
+                .iter()
+                .map(|signer| AccountMeta::new_readonly(*signer, false)),
+        );
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &SplStoreInstruction::InitializeMultisig(m),
+            accounts,
+        );
+        self.process(instruction, &[&multisig]).await?;
+        Ok(multisig.pubkey())
+    }
+
+    async fn store_account(&mut self) -> StoreAccount {
+        fetch_account_info_data::<StoreAccount>(&mut self.banks_client, self.store)
+            .await
+            .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn initialize_cannot_be_repeated() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize(5, &admin).await.unwrap();
+
+    let result = fixture.initialize(1, &admin).await;
+    assert_store_error(result, SplStoreError::StoreAlreadyInitialized);
+
+    let attacker = Keypair::new();
+    let store = fixture.store;
+    let result = fixture
+        .initialize_at(store, 1, &attacker.pubkey(), 0, &[&attacker])
+        .await;
+    assert_store_error(result, SplStoreError::InvalidStoreAddress);
+    let result = fixture
+        .initialize_at(store, 1, &admin.pubkey(), 1, &[&admin])
+        .await;
+    assert_store_error(result, SplStoreError::InvalidStoreAddress);
+
+    let acc = fixture.store_account().await;
+    assert!(acc.is_initialized);
+    assert_eq!(acc.admin, admin.pubkey());
+    assert_eq!(acc.prices, flat_prices(5));
+}
+
+#[tokio::test]
+async fn initialize_requires_admin_signature() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+
+    // Anyone could otherwise claim the stores of an admin before they do
+    let result = fixture.initialize_as(5, &admin.pubkey(), 0, &[]).await;
+    assert_store_error(result, SplStoreError::AuthorityNotSigner);
+    let store = fixture.store;
+    assert!(fixture
+        .banks_client
+        .get_account(store)
+        .await
+        .unwrap()
+        .is_none());
+
+    fixture.initialize(5, &admin).await.unwrap();
+    assert_eq!(fixture.store_account().await.admin, admin.pubkey());
+}
+
+#[tokio::test]
+async fn update_price_requires_admin_signature() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize(5, &admin).await.unwrap();
+
+    let result = fixture
+        .process_as(update_price(1), &admin.pubkey(), &[])
+        .await;
+    assert_store_error(result, SplStoreError::AuthorityNotSigner);
+    assert_eq!(fixture.store_account().await.prices, flat_prices(5));
+}
+
+#[tokio::test]
+async fn update_price_rejects_non_admin() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize(5, &admin).await.unwrap();
+
+    let attacker = Keypair::new();
+    let result = fixture
+        .process_as(update_price(1), &attacker.pubkey(), &[&attacker])
+        .await;
+    assert_store_error(result, SplStoreError::AccountLacksRole);
+    assert_eq!(fixture.store_account().await.prices, flat_prices(5));
+}
+
+#[tokio::test]
+async fn update_price_rejects_uninitialized_store() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let store = Keypair::new();
+    let program_id = fixture.program_id;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[solana_sdk::system_instruction::create_account(
+            &fixture.payer.pubkey(),
+            &store.pubkey(),
+            1_000_000_000,
+            StoreAccount::LEN as u64,
+            &program_id,
+        )],
+        Some(&fixture.payer.pubkey()),
+        &[&fixture.payer, &store],
+        fixture.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    fixture
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    fixture.store = store.pubkey();
+    let result = fixture
+        .process_as(update_price(1), &admin.pubkey(), &[&admin])
+        .await;
+    assert_store_error(result, SplStoreError::StoreNotInitialized);
+}
+
 #[tokio::test]
 async fn it_works() {
     dotenv::dotenv().ok();
@@ -130,12 +1013,14 @@ async fn it_works() {
 
     let program_id = Pubkey::new_unique();
 
-    let store = Keypair::new();
     let client = Keypair::new();
     let token_mint = Keypair::new();
     let admin = Keypair::new();
 
-    let store_ata_pubkey = get_associated_token_address(&store.pubkey(), &token_mint.pubkey());
+    let (store, _) =
+        StoreAccount::find_address(&program_id, &admin.pubkey(), &token_mint.pubkey(), 0);
+    let (vault, _) = StoreAccount::find_vault_address(&program_id, &store);
+    let store_ata_pubkey = get_associated_token_address(&store, &token_mint.pubkey());
 
     let client_ata_pubkey = get_associated_token_address(&client.pubkey(), &token_mint.pubkey());
 
@@ -161,7 +1046,7 @@ async fn it_works() {
         client.pubkey(),
         solana_sdk::account::Account {
             lamports: 69_000_000_000,
-            owner: program_id,
+            owner: system_program_pubkey,
             ..Default::default()
         },
     );
@@ -244,15 +1129,16 @@ async fn it_works() {
     let mut transaction = Transaction::new_with_payer(
         &[Instruction::new_with_borsh(
             program_id,
-            &SplStoreInstruction::Initialize(initial_price, 32_000_200_000_000),
+            &SplStoreInstruction::Initialize(flat_prices(initial_price), 32_000_200_000_000, 0),
             vec![
                 AccountMeta::new(payer.pubkey(), true),
                 AccountMeta::new(store_ata_pubkey, false),
-                AccountMeta::new(store.pubkey(), true),
+                AccountMeta::new(store, false),
                 AccountMeta::new(token_mint.pubkey(), false),
                 AccountMeta::new(system_program_pubkey, false),
                 AccountMeta::new(spl_token_program_pubkey, false),
-                AccountMeta::new(admin.pubkey(), false),
+                AccountMeta::new(admin.pubkey(), true),
+                AccountMeta::new(vault, false),
                 AccountMeta::new(ata_program_pubkey, false),
             ],
         )],
@@ -260,16 +1146,16 @@ async fn it_works() {
     );
 
     transaction.sign(
-        &[&payer, &store],
+        &[&payer, &admin],
         banks_client.get_latest_blockhash().await.unwrap(),
     );
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let acc = fetch_account_info_data::<StoreAccount>(&mut banks_client, store.pubkey())
+    let acc = fetch_account_info_data::<StoreAccount>(&mut banks_client, store)
         .await
         .unwrap();
 
-    assert_eq!(acc.price, initial_price);
+    assert_eq!(acc.prices, flat_prices(initial_price));
 
     // Mint tokens to store ATA ===============================================
 
@@ -291,28 +1177,28 @@ async fn it_works() {
 
     let instruction = Instruction::new_with_borsh(
         program_id,
-        &SplStoreInstruction::UpdatePrice(37),
+        &update_price(37 * LAMPORTS_PER_SOL),
         vec![
-            AccountMeta::new(store.pubkey(), false),
-            AccountMeta::new(admin.pubkey(), false),
+            AccountMeta::new(store, false),
+            AccountMeta::new(admin.pubkey(), true),
         ],
     );
 
     let message = Message::new(&[instruction], Some(&payer.pubkey()));
 
-    let mut transaction = Transaction::new(&[&payer], message, recent_blockhash);
+    let mut transaction = Transaction::new(&[&payer, &admin], message, recent_blockhash);
 
     transaction.sign(
-        &[&payer],
+        &[&payer, &admin],
         banks_client.get_latest_blockhash().await.unwrap(),
     );
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let acc = fetch_account_info_data::<StoreAccount>(&mut banks_client, store.pubkey())
+    let acc = fetch_account_info_data::<StoreAccount>(&mut banks_client, store)
         .await
         .unwrap();
 
-    assert_eq!(acc.price, 37);
+    assert_eq!(acc.prices, flat_prices(37 * LAMPORTS_PER_SOL));
 
     // Buy some tokens =============================================================
 
@@ -321,10 +1207,14 @@ async fn it_works() {
     let transaction = Transaction::new_signed_with_payer(
         &[Instruction::new_with_borsh(
             program_id,
-            &SplStoreInstruction::Buy(amount),
+            &SplStoreInstruction::Buy {
+                amount,
+                min_lamports: 518,
+            },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
-                AccountMeta::new(store.pubkey(), false),
+                AccountMeta::new(store, false),
+                AccountMeta::new(vault, false),
                 AccountMeta::new(store_ata_pubkey, false),
                 AccountMeta::new(client.pubkey(), true),
                 AccountMeta::new(client_ata_pubkey, false),
@@ -342,8 +1232,8 @@ async fn it_works() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     assert_eq!(
-        banks_client.get_balance(store.pubkey()).await.unwrap(),
-        31_482_201_169_280
+        banks_client.get_balance(vault).await.unwrap(),
+        32_000_200_890_362
     );
     let client_acc_data = unpack_account_data(&mut banks_client, client_ata_pubkey)
         .await
@@ -361,12 +1251,16 @@ async fn it_works() {
     let transaction = Transaction::new_signed_with_payer(
         &[Instruction::new_with_borsh(
             program_id,
-            &SplStoreInstruction::Sell(amount),
+            &SplStoreInstruction::Sell {
+                amount,
+                max_lamports: 259,
+            },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
-                AccountMeta::new(store.pubkey(), true),
+                AccountMeta::new(store, false),
+                AccountMeta::new(vault, false),
                 AccountMeta::new(store_ata_pubkey, false),
-                AccountMeta::new(client.pubkey(), false),
+                AccountMeta::new(client.pubkey(), true),
                 AccountMeta::new(client_ata_pubkey, false),
                 AccountMeta::new(token_mint.pubkey(), false),
                 AccountMeta::new(system_program_pubkey, false),
@@ -375,15 +1269,15 @@ async fn it_works() {
             ],
         )],
         Some(&payer.pubkey()),
-        &[&payer, &store],
+        &[&payer, &client],
         banks_client.get_latest_blockhash().await.unwrap(),
     );
 
     banks_client.process_transaction(transaction).await.unwrap();
 
     assert_eq!(
-        banks_client.get_balance(store.pubkey()).await.unwrap(),
-        31_741_201_169_280
+        banks_client.get_balance(vault).await.unwrap(),
+        32_000_200_890_621
     );
     let client_acc_data = unpack_account_data(&mut banks_client, client_ata_pubkey)
         .await
@@ -394,3 +1288,2120 @@ async fn it_works() {
         .unwrap();
     assert_eq!(store_acc_data.amount, 14_000_000_007);
 }
+
+#[tokio::test]
+async fn admin_handover() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let new_admin = Keypair::new();
+    fixture.initialize(5, &admin).await.unwrap();
+
+    fixture
+        .process_as(
+            SplStoreInstruction::ProposeAdmin(new_admin.pubkey()),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    let acc = fixture.store_account().await;
+    assert_eq!(acc.admin, admin.pubkey());
+    assert_eq!(acc.pending_admin, Some(new_admin.pubkey()));
+
+    let result = fixture
+        .process_as(SplStoreInstruction::AcceptAdmin, &new_admin.pubkey(), &[])
+        .await;
+    assert_store_error(result, SplStoreError::AuthorityNotSigner);
+
+    fixture
+        .process_as(
+            SplStoreInstruction::AcceptAdmin,
+            &new_admin.pubkey(),
+            &[&new_admin],
+        )
+        .await
+        .unwrap();
+    let acc = fixture.store_account().await;
+    assert_eq!(acc.admin, new_admin.pubkey());
+    assert_eq!(acc.pending_admin, None);
+    assert_eq!(acc.prices, flat_prices(5));
+
+    let result = fixture
+        .process_as(update_price(1), &admin.pubkey(), &[&admin])
+        .await;
+    assert_store_error(result, SplStoreError::AccountLacksRole);
+    fixture
+        .process_as(update_price(1), &new_admin.pubkey(), &[&new_admin])
+        .await
+        .unwrap();
+    assert_eq!(fixture.store_account().await.prices, flat_prices(1));
+}
+
+#[tokio::test]
+async fn admin_handover_rejects_attacks() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let new_admin = Keypair::new();
+    let attacker = Keypair::new();
+    let outsider = Keypair::new();
+    fixture.initialize(5, &admin).await.unwrap();
+
+    // Repeating the attacker's later transaction would be deduplicated
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::AcceptAdmin,
+            &outsider.pubkey(),
+            &[&outsider],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::NoPendingAdmin);
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::ProposeAdmin(attacker.pubkey()),
+            &admin.pubkey(),
+            &[],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AdminNotSigner);
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::ProposeAdmin(attacker.pubkey()),
+            &attacker.pubkey(),
+            &[&attacker],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+
+    fixture
+        .process_as(
+            SplStoreInstruction::ProposeAdmin(new_admin.pubkey()),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::AcceptAdmin,
+            &attacker.pubkey(),
+            &[&attacker],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotPendingAdmin);
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::CancelAdminProposal,
+            &new_admin.pubkey(),
+            &[&new_admin],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+
+    fixture
+        .process_as(
+            SplStoreInstruction::CancelAdminProposal,
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    assert_eq!(fixture.store_account().await.pending_admin, None);
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::AcceptAdmin,
+            &new_admin.pubkey(),
+            &[&new_admin],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::NoPendingAdmin);
+    assert_eq!(fixture.store_account().await.admin, admin.pubkey());
+}
+
+#[tokio::test]
+async fn multisig_admin() {
+    let mut fixture = StoreFixture::new().await;
+    let signers = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let signer_keys: Vec<Pubkey> = signers.iter().map(|signer| signer.pubkey()).collect();
+    let multisig = fixture.create_multisig(2, &signer_keys).await.unwrap();
+
+    let acc = fetch_account_info_data::<Multisig>(&mut fixture.banks_client, multisig)
+        .await
+        .unwrap();
+    assert_eq!((acc.m, acc.n), (2, 3));
+    assert_eq!(&acc.signers[..3], &signer_keys[..]);
+
+    let result = fixture.initialize_as(5, &multisig, 0, &[&signers[0]]).await;
+    assert_store_error(result, SplStoreError::NotEnoughMultisigSigners);
+    fixture
+        .initialize_as(5, &multisig, 0, &[&signers[0], &signers[2]])
+        .await
+        .unwrap();
+
+    let result = fixture
+        .process_as_multisig(update_price(1), &multisig, &[&signers[0]])
+        .await;
+    assert_store_error(result, SplStoreError::NotEnoughMultisigSigners);
+
+    let result = fixture
+        .process_as_multisig(update_price(1), &multisig, &[&signers[0], &signers[0]])
+        .await;
+    assert_store_error(result, SplStoreError::NotEnoughMultisigSigners);
+
+    let outsider = Keypair::new();
+    let result = fixture
+        .process_as_multisig(update_price(1), &multisig, &[&signers[0], &outsider])
+        .await;
+    assert_store_error(result, SplStoreError::NotEnoughMultisigSigners);
+    assert_eq!(fixture.store_account().await.prices, flat_prices(5));
+
+    fixture
+        .process_as_multisig(update_price(1), &multisig, &[&signers[0], &signers[2]])
+        .await
+        .unwrap();
+    assert_eq!(fixture.store_account().await.prices, flat_prices(1));
+
+    let new_admin = Keypair::new();
+    let result = fixture
+        .process_as_multisig(
+            SplStoreInstruction::ProposeAdmin(new_admin.pubkey()),
+            &multisig,
+            &[&signers[1]],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::NotEnoughMultisigSigners);
+    fixture
+        .process_as_multisig(
+            SplStoreInstruction::ProposeAdmin(new_admin.pubkey()),
+            &multisig,
+            &[&signers[1], &signers[2]],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.store_account().await.pending_admin,
+        Some(new_admin.pubkey())
+    );
+}
+
+#[tokio::test]
+async fn multisig_rejects_invalid_threshold() {
+    let mut fixture = StoreFixture::new().await;
+    let signers = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let result = fixture.create_multisig(0, &signers).await.map(|_| ());
+    assert_store_error(result, SplStoreError::InvalidMultisigConfig);
+    let result = fixture.create_multisig(3, &signers).await.map(|_| ());
+    assert_store_error(result, SplStoreError::InvalidMultisigConfig);
+}
+
+#[tokio::test]
+async fn multisig_rejects_duplicate_signers() {
+    let mut fixture = StoreFixture::new().await;
+    let signer = Pubkey::new_unique();
+
+    let result = fixture
+        .create_multisig(2, &[signer, Pubkey::new_unique(), signer])
+        .await
+        .map(|_| ());
+    assert_store_error(result, SplStoreError::InvalidMultisigConfig);
+}
+
+#[tokio::test]
+async fn pricer_role_is_limited_to_pricing() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let pricer = Keypair::new();
+    fixture.initialize(5, &admin).await.unwrap();
+
+    fixture
+        .process_as(
+            SplStoreInstruction::GrantRole(Role::Pricer, pricer.pubkey()),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.store_account().await.roles.pricer,
+        Some(pricer.pubkey())
+    );
+
+    fixture
+        .process_as(update_price(7), &pricer.pubkey(), &[&pricer])
+        .await
+        .unwrap();
+    assert_eq!(fixture.store_account().await.prices, flat_prices(7));
+
+    let result = fixture
+        .process_as(update_price(8), &pricer.pubkey(), &[])
+        .await;
+    assert_store_error(result, SplStoreError::AuthorityNotSigner);
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::ProposeAdmin(pricer.pubkey()),
+            &pricer.pubkey(),
+            &[&pricer],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::GrantRole(Role::Treasurer, pricer.pubkey()),
+            &pricer.pubkey(),
+            &[&pricer],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountLacksRole);
+}
+
+#[tokio::test]
+async fn role_manager_grants_and_revokes_roles() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let manager = Keypair::new();
+    let pricer = Keypair::new();
+    fixture.initialize(5, &admin).await.unwrap();
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::GrantRole(Role::RoleManager, manager.pubkey()),
+            &manager.pubkey(),
+            &[&manager],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+
+    fixture
+        .process_as(
+            SplStoreInstruction::GrantRole(Role::RoleManager, manager.pubkey()),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    fixture
+        .process_as(
+            SplStoreInstruction::GrantRole(Role::Pricer, pricer.pubkey()),
+            &manager.pubkey(),
+            &[&manager],
+        )
+        .await
+        .unwrap();
+    fixture
+        .process_as(update_price(7), &pricer.pubkey(), &[&pricer])
+        .await
+        .unwrap();
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::RevokeRole(Role::RoleManager),
+            &manager.pubkey(),
+            &[&manager],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+
+    fixture
+        .process_as(
+            SplStoreInstruction::RevokeRole(Role::Pricer),
+            &manager.pubkey(),
+            &[&manager],
+        )
+        .await
+        .unwrap();
+    let acc = fixture.store_account().await;
+    assert_eq!(acc.roles.pricer, None);
+    assert_eq!(acc.roles.role_manager, Some(manager.pubkey()));
+
+    let result = fixture
+        .process_as(update_price(9), &pricer.pubkey(), &[&pricer])
+        .await;
+    assert_store_error(result, SplStoreError::AccountLacksRole);
+    assert_eq!(fixture.store_account().await.prices, flat_prices(7));
+}
+
+#[tokio::test]
+async fn pause_trading_per_direction() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let pauser = Keypair::new();
+    let attacker = Keypair::new();
+    fixture.initialize_trading(LAMPORTS_PER_SOL, &admin).await;
+
+    fixture
+        .process_as(
+            SplStoreInstruction::SetPaused {
+                buy: true,
+                sell: false,
+            },
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    assert_store_error(fixture.buy(1).await, SplStoreError::StorePaused);
+    fixture.sell(1).await.unwrap();
+
+    fixture
+        .process_as(
+            SplStoreInstruction::GrantRole(Role::Pauser, pauser.pubkey()),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    fixture
+        .process_as(
+            SplStoreInstruction::SetPaused {
+                buy: false,
+                sell: true,
+            },
+            &pauser.pubkey(),
+            &[&pauser],
+        )
+        .await
+        .unwrap();
+    let acc = fixture.store_account().await;
+    assert!(!acc.buy_paused);
+    assert!(acc.sell_paused);
+    fixture.buy(1).await.unwrap();
+    assert_store_error(fixture.sell(1).await, SplStoreError::StorePaused);
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::SetPaused {
+                buy: false,
+                sell: false,
+            },
+            &attacker.pubkey(),
+            &[&attacker],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountLacksRole);
+    assert_store_error(fixture.sell(1).await, SplStoreError::StorePaused);
+
+    let store_ata = fixture.store_ata();
+    let client_ata = fixture.client_ata();
+    assert_eq!(fixture.token_balance(store_ata).await, 100);
+    assert_eq!(fixture.token_balance(client_ata).await, 100);
+}
+
+#[tokio::test]
+async fn close_store_drains_and_closes_accounts() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let attacker = Keypair::new();
+    fixture.initialize_trading(LAMPORTS_PER_SOL, &admin).await;
+
+    let admin_ata = fixture.admin_ata(&admin.pubkey());
+    let attacker_ata = fixture.create_ata(&attacker.pubkey()).await;
+    let result = fixture
+        .close_store(&attacker, &attacker_ata, &attacker.pubkey())
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+    // Skipping the store ATA would leave its tokens behind
+    let result = fixture
+        .close_store_with_accounts(
+            &admin,
+            &Pubkey::new_unique(),
+            vec![],
+            &admin_ata,
+            &admin.pubkey(),
+        )
+        .await;
+    assert_store_error(result, SplStoreError::InvalidAtaAddress);
+
+    let store = fixture.store;
+    let vault = fixture.vault();
+    let store_ata = fixture.store_ata();
+    let store_lamports = fixture.banks_client.get_balance(store).await.unwrap();
+    let vault_lamports = fixture.banks_client.get_balance(vault).await.unwrap();
+    let store_ata_lamports = fixture.banks_client.get_balance(store_ata).await.unwrap();
+
+    fixture
+        .close_store(&admin, &admin_ata, &admin.pubkey())
+        .await
+        .unwrap();
+
+    assert_eq!(fixture.token_balance(admin_ata).await, 100);
+    assert_eq!(
+        fixture
+            .banks_client
+            .get_balance(admin.pubkey())
+            .await
+            .unwrap(),
+        store_lamports + vault_lamports + store_ata_lamports
+    );
+    assert!(fixture
+        .banks_client
+        .get_account(store)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(fixture
+        .banks_client
+        .get_account(vault)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(fixture
+        .banks_client
+        .get_account(store_ata)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn close_store_harvests_withheld_transfer_fees() {
+    let mut fixture = StoreFixture::new_token_2022(100).await;
+    let admin = Keypair::new();
+    fixture.initialize_trading(LAMPORTS_PER_SOL, &admin).await;
+    // Selling withholds part of the transfer in the store ATA
+    fixture.sell(50).await.unwrap();
+
+    let store_ata = fixture.store_ata();
+    let admin_ata = fixture.admin_ata(&admin.pubkey());
+    fixture
+        .close_store(&admin, &admin_ata, &admin.pubkey())
+        .await
+        .unwrap();
+    assert!(fixture
+        .banks_client
+        .get_account(store_ata)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(fixture
+        .banks_client
+        .get_account(fixture.store)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn deposit_and_withdraw_sol() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let treasurer = Keypair::new();
+    let pricer = Keypair::new();
+    fixture.initialize(1, &admin).await.unwrap();
+    for (role, holder) in [(Role::Treasurer, &treasurer), (Role::Pricer, &pricer)] {
+        fixture
+            .process_as(
+                SplStoreInstruction::GrantRole(role, holder.pubkey()),
+                &admin.pubkey(),
+                &[&admin],
+            )
+            .await
+            .unwrap();
+    }
+
+    let store = fixture.store;
+    let vault = fixture.vault();
+    let rent = fixture.banks_client.get_rent().await.unwrap();
+    let rent_exempt_minimum = rent.minimum_balance(0);
+    assert_eq!(
+        fixture.balance(store).await,
+        rent.minimum_balance(StoreAccount::LEN)
+    );
+    assert_eq!(
+        fixture.balance(vault).await,
+        rent_exempt_minimum + 10_000_000_000
+    );
+
+    fixture.deposit_sol(5_000_000_000).await.unwrap();
+    assert_eq!(
+        fixture.balance(vault).await,
+        rent_exempt_minimum + 15_000_000_000
+    );
+
+    let result = fixture.withdraw_sol(1, &pricer.pubkey(), &pricer).await;
+    assert_store_error(result, SplStoreError::AccountLacksRole);
+
+    fixture
+        .withdraw_sol(3_000_000_000, &treasurer.pubkey(), &treasurer)
+        .await
+        .unwrap();
+    assert_eq!(fixture.balance(treasurer.pubkey()).await, 3_000_000_000);
+
+    let result = fixture
+        .withdraw_sol(12_000_000_001, &admin.pubkey(), &admin)
+        .await;
+    assert_store_error(result, SplStoreError::WithdrawalBreaksRentExemption);
+
+    fixture
+        .withdraw_sol(12_000_000_000, &admin.pubkey(), &admin)
+        .await
+        .unwrap();
+    assert_eq!(fixture.balance(vault).await, rent_exempt_minimum);
+    assert_eq!(fixture.balance(admin.pubkey()).await, 12_000_000_000);
+}
+
+#[tokio::test]
+async fn deposit_and_withdraw_tokens() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let treasurer = Keypair::new();
+    let attacker = Keypair::new();
+    fixture.initialize(1, &admin).await.unwrap();
+    fixture
+        .process_as(
+            SplStoreInstruction::GrantRole(Role::Treasurer, treasurer.pubkey()),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+
+    let store_ata = fixture.store_ata();
+    let admin_ata = fixture.create_ata(&admin.pubkey()).await;
+    let treasurer_ata = fixture.create_ata(&treasurer.pubkey()).await;
+    let attacker_ata = fixture.create_ata(&attacker.pubkey()).await;
+    fixture.mint_to(&admin_ata, 50).await;
+    fixture.mint_to(&attacker_ata, 50).await;
+
+    let result = fixture
+        .deposit_tokens(10, &attacker_ata, &attacker, &attacker)
+        .await;
+    assert_store_error(result, SplStoreError::AccountLacksRole);
+
+    fixture
+        .deposit_tokens(50, &admin_ata, &admin, &admin)
+        .await
+        .unwrap();
+    assert_eq!(fixture.token_balance(store_ata).await, 50);
+    assert_eq!(fixture.token_balance(admin_ata).await, 0);
+
+    let result = fixture.withdraw_tokens(50, &attacker_ata, &attacker).await;
+    assert_store_error(result, SplStoreError::AccountLacksRole);
+
+    fixture
+        .withdraw_tokens(20, &treasurer_ata, &treasurer)
+        .await
+        .unwrap();
+    fixture
+        .withdraw_tokens(30, &admin_ata, &admin)
+        .await
+        .unwrap();
+    assert_eq!(fixture.token_balance(store_ata).await, 0);
+    assert_eq!(fixture.token_balance(treasurer_ata).await, 20);
+    assert_eq!(fixture.token_balance(admin_ata).await, 30);
+}
+
+#[tokio::test]
+async fn initialize_prefunded_store_address() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let (store, _) = StoreAccount::find_address(
+        &fixture.program_id,
+        &admin.pubkey(),
+        &fixture.token_mint.pubkey(),
+        0,
+    );
+
+    let instruction =
+        solana_sdk::system_instruction::transfer(&fixture.payer.pubkey(), &store, 1_000_000);
+    fixture.process(instruction, &[]).await.unwrap();
+
+    fixture.initialize(5, &admin).await.unwrap();
+    assert_eq!(fixture.store, store);
+    let acc = fixture.store_account().await;
+    assert_eq!(acc.admin, admin.pubkey());
+    assert_eq!(acc.creator, admin.pubkey());
+}
+
+#[tokio::test]
+async fn trading_rejects_foreign_mint() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize_trading(LAMPORTS_PER_SOL, &admin).await;
+
+    // Pretend the client trades another mint through the same store
+    fixture.token_mint = fixture.create_mint(0).await;
+    fixture.create_client_ata().await;
+    let client_ata = fixture.client_ata();
+    fixture.mint_to(&client_ata, 100).await;
+
+    assert_store_error(fixture.buy(1).await, SplStoreError::MintNotListed);
+    assert_store_error(fixture.sell(1).await, SplStoreError::MintNotListed);
+}
+
+#[tokio::test]
+async fn trading_settles_through_the_vault_only() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize_trading(LAMPORTS_PER_SOL, &admin).await;
+
+    let store = fixture.store;
+    let store_lamports = fixture.balance(store).await;
+    let vault_lamports = fixture.balance(fixture.vault()).await;
+
+    fixture.buy(2).await.unwrap();
+    assert_eq!(
+        fixture.balance(fixture.vault()).await,
+        vault_lamports - 2_000_000_000
+    );
+    fixture.sell(1).await.unwrap();
+    assert_eq!(
+        fixture.balance(fixture.vault()).await,
+        vault_lamports - 1_000_000_000
+    );
+    assert_eq!(fixture.balance(store).await, store_lamports);
+
+    // Another store's vault cannot stand in for this one
+    let other_admin = Keypair::new();
+    fixture.initialize(1, &other_admin).await.unwrap();
+    let other_vault = fixture.vault();
+    fixture.store = store;
+    let instruction = Instruction::new_with_borsh(
+        fixture.program_id,
+        &SplStoreInstruction::WithdrawSol(1),
+        vec![
+            AccountMeta::new_readonly(store, false),
+            AccountMeta::new(other_vault, false),
+            AccountMeta::new(admin.pubkey(), false),
+            AccountMeta::new_readonly(admin.pubkey(), true),
+        ],
+    );
+    let result = fixture.process(instruction, &[&admin]).await;
+    assert_store_error(result, SplStoreError::InvalidVaultAddress);
+}
+
+#[tokio::test]
+async fn sell_charges_system_owned_client() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture
+        .initialize_trading(2 * LAMPORTS_PER_SOL, &admin)
+        .await;
+
+    let client = fixture.client.pubkey();
+    let client_lamports = fixture.balance(client).await;
+    let vault_lamports = fixture.balance(fixture.vault()).await;
+
+    fixture.sell(3).await.unwrap();
+    assert_eq!(
+        fixture.balance(client).await,
+        client_lamports - 6_000_000_000
+    );
+    assert_eq!(
+        fixture.balance(fixture.vault()).await,
+        vault_lamports + 6_000_000_000
+    );
+    assert_eq!(fixture.token_balance(fixture.client_ata()).await, 103);
+
+    // The client has to authorize the payment
+    let instruction = Instruction::new_with_borsh(
+        fixture.program_id,
+        &SplStoreInstruction::Sell {
+            amount: 1,
+            max_lamports: u64::MAX,
+        },
+        vec![
+            AccountMeta::new(fixture.payer.pubkey(), true),
+            AccountMeta::new_readonly(fixture.store, false),
+            AccountMeta::new(fixture.vault(), false),
+            AccountMeta::new(fixture.store_ata(), false),
+            AccountMeta::new(client, false),
+            AccountMeta::new(fixture.client_ata(), false),
+            AccountMeta::new(fixture.token_mint.pubkey(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    let result = fixture.process(instruction, &[]).await;
+    assert_store_error(result, SplStoreError::AccountNotSigner);
+}
+
+#[tokio::test]
+async fn stores_are_independent_per_index() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize_indexed(3, &admin, 0).await.unwrap();
+    let first = fixture.store;
+    fixture.initialize_indexed(7, &admin, 1).await.unwrap();
+    let second = fixture.store;
+    assert_ne!(first, second);
+
+    let acc = fixture.store_account().await;
+    assert_eq!(acc.prices, flat_prices(7));
+    assert_eq!(acc.index, 1);
+    assert_eq!(acc.mint, fixture.token_mint.pubkey());
+
+    fixture
+        .process_as(update_price(9), &admin.pubkey(), &[&admin])
+        .await
+        .unwrap();
+    assert_eq!(fixture.store_account().await.prices, flat_prices(9));
+
+    fixture.store = first;
+    let acc = fixture.store_account().await;
+    assert_eq!(acc.prices, flat_prices(3));
+    assert_eq!(acc.index, 0);
+    assert_ne!(
+        fixture.vault(),
+        StoreAccount::find_vault_address(&fixture.program_id, &second).0
+    );
+}
+
+#[tokio::test]
+async fn catalog_lists_additional_mints() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let pricer = Keypair::new();
+    fixture.initialize_trading(LAMPORTS_PER_SOL, &admin).await;
+    fixture
+        .process_as(
+            SplStoreInstruction::GrantRole(Role::Pricer, pricer.pubkey()),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+
+    let listed = fixture.create_mint(0).await;
+    let listed_mint = listed.pubkey();
+    let own_mint = fixture.token_mint.pubkey();
+    let result = fixture
+        .add_catalog_entry(3 * LAMPORTS_PER_SOL, &listed_mint, &pricer)
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+    let result = fixture
+        .add_catalog_entry(3 * LAMPORTS_PER_SOL, &own_mint, &admin)
+        .await;
+    assert_store_error(result, SplStoreError::MintAlreadyListed);
+    fixture
+        .add_catalog_entry(3 * LAMPORTS_PER_SOL, &listed_mint, &admin)
+        .await
+        .unwrap();
+    // Transactions differ from earlier ones, identical ones would be deduplicated
+    let result = fixture
+        .add_catalog_entry(4 * LAMPORTS_PER_SOL, &listed_mint, &admin)
+        .await;
+    assert_store_error(result, SplStoreError::MintAlreadyListed);
+
+    // Trade the listed mint through the same store and vault
+    let own = std::mem::replace(&mut fixture.token_mint, listed);
+    fixture.create_client_ata().await;
+    let client_ata = fixture.client_ata();
+    fixture.mint_to(&client_ata, 10).await;
+    let store = fixture.store;
+    let store_ata = fixture.create_ata(&store).await;
+    fixture.mint_to(&store_ata, 10).await;
+
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    fixture.buy(2).await.unwrap();
+    fixture.sell(1).await.unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 3_000_000_000);
+    assert_eq!(fixture.token_balance(store_ata).await, 11);
+
+    let update = |price, enabled| SplStoreInstruction::UpdateCatalogEntry {
+        mint: listed_mint,
+        prices: flat_prices(price),
+        enabled,
+    };
+    fixture
+        .process_as(
+            update(4 * LAMPORTS_PER_SOL, false),
+            &pricer.pubkey(),
+            &[&pricer],
+        )
+        .await
+        .unwrap();
+    assert_store_error(fixture.buy(3).await, SplStoreError::MintDisabled);
+    fixture
+        .process_as(
+            update(4 * LAMPORTS_PER_SOL, true),
+            &pricer.pubkey(),
+            &[&pricer],
+        )
+        .await
+        .unwrap();
+    let vault_lamports = fixture.balance(vault).await;
+    fixture.buy(1).await.unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 4_000_000_000);
+
+    // Closing the store would strand its inventory of listed mints
+    let listed = std::mem::replace(&mut fixture.token_mint, own);
+    let admin_ata = fixture.admin_ata(&admin.pubkey());
+    let result = fixture
+        .close_store(&admin, &admin_ata, &admin.pubkey())
+        .await;
+    assert_store_error(result, SplStoreError::CatalogNotEmpty);
+    let own = std::mem::replace(&mut fixture.token_mint, listed);
+
+    // Delisting would strand the inventory as well, until it is withdrawn
+    let instruction = fixture.remove_catalog_entry(&admin.pubkey());
+    let result = fixture.process(instruction, &[&admin]).await;
+    assert_store_error(result, SplStoreError::CatalogMintNotWithdrawn);
+    let admin_listed_ata = fixture.create_ata(&admin.pubkey()).await;
+    let instructions = [
+        fixture.withdraw_tokens_instruction(12, &admin_listed_ata, &admin.pubkey()),
+        fixture.remove_catalog_entry(&admin.pubkey()),
+    ];
+    let recent_blockhash = fixture.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&fixture.payer.pubkey()),
+        &[&fixture.payer, &admin],
+        recent_blockhash,
+    );
+    fixture
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    assert_eq!(fixture.token_balance(admin_listed_ata).await, 12);
+    assert_store_error(fixture.buy(2).await, SplStoreError::MintNotListed);
+    let result = fixture
+        .process_as(
+            update(5 * LAMPORTS_PER_SOL, true),
+            &pricer.pubkey(),
+            &[&pricer],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::MintNotListed);
+
+    // The store's own token is unaffected
+    fixture.token_mint = own;
+    fixture.buy(1).await.unwrap();
+    assert!(fixture.store_account().await.catalog.entries.is_empty());
+}
+
+#[tokio::test]
+async fn trading_earns_the_spread() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture
+        .initialize_trading(2 * LAMPORTS_PER_SOL, &admin)
+        .await;
+
+    let set_prices = |bid, ask| SplStoreInstruction::UpdatePrice { bid, ask };
+    fixture
+        .process_as(
+            set_prices(Some(LAMPORTS_PER_SOL), Some(3 * LAMPORTS_PER_SOL)),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    let result = fixture
+        .process_as(
+            set_prices(Some(4 * LAMPORTS_PER_SOL), None),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::BidExceedsAsk);
+    let result = fixture
+        .process_as(set_prices(None, Some(0)), &admin.pubkey(), &[&admin])
+        .await;
+    assert_store_error(result, SplStoreError::BidExceedsAsk);
+    assert_eq!(
+        fixture.store_account().await.prices,
+        Prices {
+            bid: LAMPORTS_PER_SOL,
+            ask: 3 * LAMPORTS_PER_SOL
+        }
+    );
+
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    fixture.buy(2).await.unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 2_000_000_000);
+    fixture.sell(2).await.unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports + 4_000_000_000);
+
+    fixture
+        .process_as(
+            set_prices(None, Some(5 * LAMPORTS_PER_SOL)),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.store_account().await.prices,
+        Prices {
+            bid: LAMPORTS_PER_SOL,
+            ask: 5 * LAMPORTS_PER_SOL
+        }
+    );
+
+    let listed = fixture.create_mint(0).await;
+    let instruction = Instruction::new_with_borsh(
+        fixture.program_id,
+        &SplStoreInstruction::AddCatalogEntry(Prices { bid: 2, ask: 1 }),
+        vec![
+            AccountMeta::new(fixture.store, false),
+            AccountMeta::new_readonly(listed.pubkey(), false),
+            AccountMeta::new_readonly(admin.pubkey(), true),
+        ],
+    );
+    let result = fixture.process(instruction, &[&admin]).await;
+    assert_store_error(result, SplStoreError::BidExceedsAsk);
+}
+
+#[tokio::test]
+async fn prices_are_per_whole_token() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.token_mint = fixture.create_mint(6).await;
+    // 0.0025 SOL per token
+    fixture.initialize_trading(2_500_000, &admin).await;
+    let client_ata = fixture.client_ata();
+    let store_ata = fixture.store_ata();
+    fixture.mint_to(&client_ata, 3_500_000).await;
+    fixture.mint_to(&store_ata, 3_500_000).await;
+
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    // 3.5 tokens
+    fixture.buy(3_500_000).await.unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 8_750_000);
+    fixture.sell(1_000_000).await.unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 6_250_000);
+}
+
+#[tokio::test]
+async fn trades_respect_client_price_limits() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture
+        .initialize_trading(2 * LAMPORTS_PER_SOL, &admin)
+        .await;
+
+    // The client quoted 2 SOL per token, then the price moved against it
+    fixture
+        .process_as(
+            SplStoreInstruction::UpdatePrice {
+                bid: Some(LAMPORTS_PER_SOL),
+                ask: Some(3 * LAMPORTS_PER_SOL),
+            },
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+
+    let result = fixture
+        .trade(SplStoreInstruction::Buy {
+            amount: 2,
+            min_lamports: 4 * LAMPORTS_PER_SOL,
+        })
+        .await;
+    assert_store_error(result, SplStoreError::SlippageExceeded);
+    let result = fixture
+        .trade(SplStoreInstruction::Sell {
+            amount: 2,
+            max_lamports: 4 * LAMPORTS_PER_SOL,
+        })
+        .await;
+    assert_store_error(result, SplStoreError::SlippageExceeded);
+
+    // Limits matching the current prices exactly pass
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    fixture
+        .trade(SplStoreInstruction::Buy {
+            amount: 2,
+            min_lamports: 2 * LAMPORTS_PER_SOL,
+        })
+        .await
+        .unwrap();
+    fixture
+        .trade(SplStoreInstruction::Sell {
+            amount: 2,
+            max_lamports: 6 * LAMPORTS_PER_SOL,
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.balance(vault).await,
+        vault_lamports + 4 * LAMPORTS_PER_SOL
+    );
+}
+
+#[tokio::test]
+async fn quoted_trades() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let quote_signer = Keypair::new();
+    fixture.initialize_trading(LAMPORTS_PER_SOL, &admin).await;
+
+    let expiry_slot = fixture.slot().await + 1_000;
+    let quote = |price, amount, nonce| Quote {
+        price,
+        amount,
+        expiry_slot,
+        nonce,
+    };
+    let buy = quote(3 * LAMPORTS_PER_SOL, 2, 1);
+    let buy_message = fixture.quote_message(QuoteSide::Buy, buy);
+    let result = fixture
+        .trade_with_quote(
+            SplStoreInstruction::BuyWithQuote(buy),
+            &buy_message,
+            &quote_signer,
+        )
+        .await;
+    assert_instruction_error(result, 1, SplStoreError::QuoteSignerNotSet);
+
+    let set_signer = || SplStoreInstruction::SetQuoteSigner(Some(quote_signer.pubkey()));
+    let result = fixture
+        .process_as(set_signer(), &quote_signer.pubkey(), &[&quote_signer])
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+    fixture
+        .process_as(set_signer(), &admin.pubkey(), &[&admin])
+        .await
+        .unwrap();
+
+    // Trades at the quoted price instead of the listed one
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    fixture
+        .trade_with_quote(
+            SplStoreInstruction::BuyWithQuote(buy),
+            &buy_message,
+            &quote_signer,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.balance(vault).await,
+        vault_lamports - 6 * LAMPORTS_PER_SOL
+    );
+    assert_eq!(fixture.store_account().await.last_quote_nonce, 1);
+
+    // A used nonce is refused
+    let reused = quote(3 * LAMPORTS_PER_SOL, 1, 1);
+    let result = fixture
+        .trade_with_quote(
+            SplStoreInstruction::BuyWithQuote(reused),
+            &fixture.quote_message(QuoteSide::Buy, reused),
+            &quote_signer,
+        )
+        .await;
+    assert_instruction_error(result, 1, SplStoreError::QuoteNonceUsed);
+
+    // A buy quote cannot be used to sell, nor a quote signed by someone else
+    let sell = quote(2 * LAMPORTS_PER_SOL, 1, 2);
+    let result = fixture
+        .trade_with_quote(
+            SplStoreInstruction::SellWithQuote(sell),
+            &fixture.quote_message(QuoteSide::Buy, sell),
+            &quote_signer,
+        )
+        .await;
+    assert_instruction_error(result, 1, SplStoreError::InvalidQuoteSignature);
+    let result = fixture
+        .trade_with_quote(
+            SplStoreInstruction::SellWithQuote(sell),
+            &fixture.quote_message(QuoteSide::Sell, sell),
+            &admin,
+        )
+        .await;
+    assert_instruction_error(result, 1, SplStoreError::InvalidQuoteSignature);
+
+    let vault_lamports = fixture.balance(vault).await;
+    fixture
+        .trade_with_quote(
+            SplStoreInstruction::SellWithQuote(sell),
+            &fixture.quote_message(QuoteSide::Sell, sell),
+            &quote_signer,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.balance(vault).await,
+        vault_lamports + 2 * LAMPORTS_PER_SOL
+    );
+
+    // Quotes are bound to the client
+    let other_client = QuoteMessage {
+        client: Pubkey::new_unique(),
+        ..fixture.quote_message(QuoteSide::Sell, quote(2 * LAMPORTS_PER_SOL, 1, 3))
+    };
+    let result = fixture
+        .trade_with_quote(
+            SplStoreInstruction::SellWithQuote(other_client.quote),
+            &other_client,
+            &quote_signer,
+        )
+        .await;
+    assert_instruction_error(result, 1, SplStoreError::InvalidQuoteSignature);
+
+    // Quotes can land out of order, each nonce still used once
+    for nonce in [5, 4] {
+        let sell = quote(2 * LAMPORTS_PER_SOL, 1, nonce);
+        fixture
+            .trade_with_quote(
+                SplStoreInstruction::SellWithQuote(sell),
+                &fixture.quote_message(QuoteSide::Sell, sell),
+                &quote_signer,
+            )
+            .await
+            .unwrap();
+    }
+    let acc = fixture.store_account().await;
+    assert_eq!(acc.last_quote_nonce, 5);
+    for nonce in [4, 5] {
+        let reused = quote(2 * LAMPORTS_PER_SOL, 2, nonce);
+        let result = fixture
+            .trade_with_quote(
+                SplStoreInstruction::SellWithQuote(reused),
+                &fixture.quote_message(QuoteSide::Sell, reused),
+                &quote_signer,
+            )
+            .await;
+        assert_instruction_error(result, 1, SplStoreError::QuoteNonceUsed);
+    }
+
+    // Nonces further below the greatest one than the window are refused
+    let latest = quote(2 * LAMPORTS_PER_SOL, 1, 6 + QUOTE_NONCE_WINDOW);
+    fixture
+        .trade_with_quote(
+            SplStoreInstruction::SellWithQuote(latest),
+            &fixture.quote_message(QuoteSide::Sell, latest),
+            &quote_signer,
+        )
+        .await
+        .unwrap();
+    let stale = quote(2 * LAMPORTS_PER_SOL, 1, 3);
+    let result = fixture
+        .trade_with_quote(
+            SplStoreInstruction::SellWithQuote(stale),
+            &fixture.quote_message(QuoteSide::Sell, stale),
+            &quote_signer,
+        )
+        .await;
+    assert_instruction_error(result, 1, SplStoreError::QuoteNonceUsed);
+    let in_window = quote(2 * LAMPORTS_PER_SOL, 1, 6);
+    fixture
+        .trade_with_quote(
+            SplStoreInstruction::SellWithQuote(in_window),
+            &fixture.quote_message(QuoteSide::Sell, in_window),
+            &quote_signer,
+        )
+        .await
+        .unwrap();
+
+    // A fee set after the quote was signed does not apply to it
+    fixture
+        .process_as(
+            SplStoreInstruction::SetFee {
+                fee_bps: 100,
+                fee_recipient: admin.pubkey(),
+            },
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    let sell = quote(2 * LAMPORTS_PER_SOL, 1, 7);
+    let result = fixture
+        .trade_with_quote(
+            SplStoreInstruction::SellWithQuote(sell),
+            &fixture.quote_message(QuoteSide::Sell, sell),
+            &quote_signer,
+        )
+        .await;
+    assert_instruction_error(result, 1, SplStoreError::InvalidQuoteSignature);
+    let vault_lamports = fixture.balance(vault).await;
+    fixture
+        .trade_with_quote(
+            SplStoreInstruction::SellWithQuote(sell),
+            &QuoteMessage {
+                fee_bps: 100,
+                ..fixture.quote_message(QuoteSide::Sell, sell)
+            },
+            &quote_signer,
+        )
+        .await
+        .unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports + 2_020_000_000);
+
+    let expired = Quote {
+        expiry_slot: 0,
+        ..quote(LAMPORTS_PER_SOL, 1, 3)
+    };
+    let result = fixture
+        .trade_with_quote(
+            SplStoreInstruction::SellWithQuote(expired),
+            &fixture.quote_message(QuoteSide::Sell, expired),
+            &quote_signer,
+        )
+        .await;
+    assert_instruction_error(result, 1, SplStoreError::QuoteExpired);
+}
+
+#[tokio::test]
+async fn trades_settle_in_quote_mint() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize_trading(LAMPORTS_PER_SOL, &admin).await;
+    let quote_mint = fixture.create_mint(6).await.pubkey();
+
+    let set_quote_mint = |mint| SplStoreInstruction::SetQuoteMint(Some(mint));
+    let client = fixture.client.pubkey();
+    let result = fixture
+        .process_as(
+            set_quote_mint(quote_mint),
+            &client,
+            &[&fixture.client.insecure_clone()],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+    let own_mint = fixture.token_mint.pubkey();
+    let result = fixture
+        .process_as(set_quote_mint(own_mint), &admin.pubkey(), &[&admin])
+        .await;
+    assert_store_error(result, SplStoreError::InvalidQuoteMint);
+    fixture
+        .process_as(set_quote_mint(quote_mint), &admin.pubkey(), &[&admin])
+        .await
+        .unwrap();
+    // 2.5 quote tokens per token
+    fixture
+        .process_as(update_price(2_500_000), &admin.pubkey(), &[&admin])
+        .await
+        .unwrap();
+
+    let store = fixture.store;
+    let store_quote_ata = get_associated_token_address(&store, &quote_mint);
+    let client_quote_ata = get_associated_token_address(&client, &quote_mint);
+    let instruction = create_associated_token_account(
+        &fixture.payer.pubkey(),
+        &store,
+        &quote_mint,
+        &spl_token::id(),
+    );
+    fixture.process(instruction, &[]).await.unwrap();
+    fixture
+        .mint_tokens_to(&quote_mint, &store_quote_ata, 10_000_000)
+        .await;
+
+    // Trading against SOL needs the quote mint accounts
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    let result = fixture
+        .trade_in_quote_mint(
+            SplStoreInstruction::Buy {
+                amount: 2,
+                min_lamports: 0,
+            },
+            &own_mint,
+        )
+        .await;
+    assert_store_error(result, SplStoreError::QuoteMintMismatch);
+
+    // The client quote ATA is created on the first purchase
+    fixture
+        .trade_in_quote_mint(
+            SplStoreInstruction::Buy {
+                amount: 2,
+                min_lamports: 5_000_000,
+            },
+            &quote_mint,
+        )
+        .await
+        .unwrap();
+    assert_eq!(fixture.token_balance(client_quote_ata).await, 5_000_000);
+    assert_eq!(fixture.token_balance(store_quote_ata).await, 5_000_000);
+
+    fixture
+        .trade_in_quote_mint(
+            SplStoreInstruction::Sell {
+                amount: 1,
+                max_lamports: 2_500_000,
+            },
+            &quote_mint,
+        )
+        .await
+        .unwrap();
+    assert_eq!(fixture.token_balance(client_quote_ata).await, 2_500_000);
+    assert_eq!(fixture.token_balance(store_quote_ata).await, 7_500_000);
+    assert_eq!(fixture.token_balance(fixture.client_ata()).await, 99);
+    assert_eq!(fixture.balance(vault).await, vault_lamports);
+
+    let result = fixture
+        .trade_in_quote_mint(
+            SplStoreInstruction::Sell {
+                amount: 2,
+                max_lamports: u64::MAX,
+            },
+            &quote_mint,
+        )
+        .await;
+    assert_store_error(result, SplStoreError::InsufficientFundsForTransaction);
+
+    // The store cannot be closed before its quote mint is withdrawn
+    let admin_ata = fixture.admin_ata(&admin.pubkey());
+    let store_ata = fixture.store_ata();
+    let quote_accounts = vec![
+        AccountMeta::new(store_quote_ata, false),
+        AccountMeta::new(quote_mint, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let result = fixture
+        .close_store_with_accounts(
+            &admin,
+            &store_ata,
+            quote_accounts.clone(),
+            &admin_ata,
+            &admin.pubkey(),
+        )
+        .await;
+    assert_store_error(result, SplStoreError::QuoteMintNotWithdrawn);
+
+    let admin_quote_ata = get_associated_token_address(&admin.pubkey(), &quote_mint);
+    let instruction = create_associated_token_account(
+        &fixture.payer.pubkey(),
+        &admin.pubkey(),
+        &quote_mint,
+        &spl_token::id(),
+    );
+    fixture.process(instruction, &[]).await.unwrap();
+    let instruction = Instruction::new_with_borsh(
+        fixture.program_id,
+        &SplStoreInstruction::WithdrawTokens(7_500_000),
+        vec![
+            AccountMeta::new_readonly(store, false),
+            AccountMeta::new(store_quote_ata, false),
+            AccountMeta::new(admin_quote_ata, false),
+            AccountMeta::new_readonly(quote_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(admin.pubkey(), true),
+        ],
+    );
+    fixture.process(instruction, &[&admin]).await.unwrap();
+    assert_eq!(fixture.token_balance(admin_quote_ata).await, 7_500_000);
+
+    // The emptied quote mint ATA is closed with the store
+    fixture
+        .close_store_with_accounts(
+            &admin,
+            &store_ata,
+            quote_accounts,
+            &admin_ata,
+            &admin.pubkey(),
+        )
+        .await
+        .unwrap();
+    assert!(fixture
+        .banks_client
+        .get_account(store_quote_ata)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(fixture
+        .banks_client
+        .get_account(store)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn quote_mint_of_another_token_program_charges_its_transfer_fee() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize_trading(LAMPORTS_PER_SOL, &admin).await;
+    // A Token-2022 quote mint with a 1% transfer fee for an SPL Token store
+    let quote_mint = fixture.create_mint_2022(100, 6).await.pubkey();
+    fixture
+        .process_as(
+            SplStoreInstruction::SetQuoteMint(Some(quote_mint)),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    fixture
+        .process_as(update_price(2_500_000), &admin.pubkey(), &[&admin])
+        .await
+        .unwrap();
+
+    let store = fixture.store;
+    let client = fixture.client.pubkey();
+    let quote_ata = |owner| {
+        get_associated_token_address_with_program_id(owner, &quote_mint, &spl_token_2022::id())
+    };
+    let store_quote_ata = quote_ata(&store);
+    let client_quote_ata = quote_ata(&client);
+    let instruction = create_associated_token_account(
+        &fixture.payer.pubkey(),
+        &store,
+        &quote_mint,
+        &spl_token_2022::id(),
+    );
+    fixture.process(instruction, &[]).await.unwrap();
+    fixture
+        .mint_tokens_to(&quote_mint, &store_quote_ata, 10_000_000)
+        .await;
+
+    // The client receives the payment less the transfer fee, which counts against its limit
+    let result = fixture
+        .trade_in_quote_mint(
+            SplStoreInstruction::Buy {
+                amount: 2,
+                min_lamports: 5_000_000,
+            },
+            &quote_mint,
+        )
+        .await;
+    assert_store_error(result, SplStoreError::SlippageExceeded);
+    fixture
+        .trade_in_quote_mint(
+            SplStoreInstruction::Buy {
+                amount: 2,
+                min_lamports: 4_950_000,
+            },
+            &quote_mint,
+        )
+        .await
+        .unwrap();
+    assert_eq!(fixture.token_balance(client_quote_ata).await, 4_950_000);
+    assert_eq!(fixture.token_balance(store_quote_ata).await, 5_000_000);
+
+    // and pays the transfer fee on top of the payment, within its limit
+    let result = fixture
+        .trade_in_quote_mint(
+            SplStoreInstruction::Sell {
+                amount: 1,
+                max_lamports: 2_500_000,
+            },
+            &quote_mint,
+        )
+        .await;
+    assert_store_error(result, SplStoreError::SlippageExceeded);
+    fixture
+        .trade_in_quote_mint(
+            SplStoreInstruction::Sell {
+                amount: 1,
+                max_lamports: 2_600_000,
+            },
+            &quote_mint,
+        )
+        .await
+        .unwrap();
+    assert_eq!(fixture.token_balance(store_quote_ata).await, 7_500_000);
+    assert_eq!(
+        fixture.token_balance(client_quote_ata).await,
+        4_950_000 - 2_525_253
+    );
+}
+
+#[tokio::test]
+async fn token_2022_transfer_fees_are_not_paid_for() {
+    // 1% transfer fee, rounded up
+    let mut fixture = StoreFixture::new_token_2022(100).await;
+    let admin = Keypair::new();
+    let price = LAMPORTS_PER_SOL / 10;
+    fixture.initialize_trading(price, &admin).await;
+    let store_ata = fixture.store_ata();
+    let client_ata = fixture.client_ata();
+    assert_eq!(fixture.token_balance(store_ata).await, 99);
+
+    // The store receives 49 of the 50 tokens and pays for those only
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    fixture.buy(50).await.unwrap();
+    assert_eq!(fixture.token_balance(store_ata).await, 148);
+    assert_eq!(fixture.token_balance(client_ata).await, 50);
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 49 * price);
+
+    // The client pays for the 10 tokens leaving the store and receives 9
+    fixture.sell(10).await.unwrap();
+    assert_eq!(fixture.token_balance(store_ata).await, 138);
+    assert_eq!(fixture.token_balance(client_ata).await, 59);
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 39 * price);
+
+    // The legacy token program cannot move Token-2022 tokens
+    fixture.token_program = spl_token::id();
+    let result = fixture.buy(1).await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::IncorrectProgramId)
+    );
+}
+
+#[tokio::test]
+async fn trades_settle_in_wrapped_sol() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    let price = LAMPORTS_PER_SOL;
+    fixture.initialize_trading(price, &admin).await;
+
+    let native_mint = spl_token::native_mint::id();
+    let client = fixture.client.pubkey();
+    let client_wsol = get_associated_token_address(&client, &native_mint);
+    let store_wsol = get_associated_token_address(&fixture.store, &native_mint);
+    let instructions = [
+        create_associated_token_account(
+            &fixture.payer.pubkey(),
+            &client,
+            &native_mint,
+            &spl_token::id(),
+        ),
+        solana_sdk::system_instruction::transfer(
+            &fixture.payer.pubkey(),
+            &client_wsol,
+            5 * LAMPORTS_PER_SOL,
+        ),
+        spl_token::instruction::sync_native(&spl_token::id(), &client_wsol).unwrap(),
+    ];
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&fixture.payer.pubkey()),
+        &[&fixture.payer],
+        fixture.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    fixture
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    let client_lamports = fixture.balance(client).await;
+    fixture
+        .trade_with_accounts(
+            SplStoreInstruction::BuyWithWrappedSol {
+                amount: 2,
+                min_lamports: 2 * price,
+            },
+            vec![AccountMeta::new(client_wsol, false)],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.token_balance(client_wsol).await,
+        7 * LAMPORTS_PER_SOL
+    );
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 2 * price);
+
+    // The store unwraps the payment and closes its wSOL ATA again
+    let payer_lamports = fixture.balance(fixture.payer.pubkey()).await;
+    fixture
+        .trade_with_accounts(
+            SplStoreInstruction::SellWithWrappedSol {
+                amount: 3,
+                max_lamports: 3 * price,
+            },
+            vec![
+                AccountMeta::new(client_wsol, false),
+                AccountMeta::new(store_wsol, false),
+                AccountMeta::new_readonly(native_mint, false),
+                AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.token_balance(client_wsol).await,
+        4 * LAMPORTS_PER_SOL
+    );
+    assert_eq!(fixture.balance(vault).await, vault_lamports + price);
+    assert_eq!(fixture.balance(client).await, client_lamports);
+    assert!(fixture
+        .banks_client
+        .get_account(store_wsol)
+        .await
+        .unwrap()
+        .is_none());
+    // Only the transaction fee is spent, the ATA rent is refunded
+    assert_eq!(
+        fixture.balance(fixture.payer.pubkey()).await,
+        payer_lamports - 10_000
+    );
+
+    // Wrapped SOL has to be of the native mint
+    let client_ata = fixture.client_ata();
+    let result = fixture
+        .trade_with_accounts(
+            SplStoreInstruction::BuyWithWrappedSol {
+                amount: 1,
+                min_lamports: 0,
+            },
+            vec![AccountMeta::new(client_ata, false)],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::WrongAccountMint);
+}
+
+#[tokio::test]
+async fn trades_pay_the_store_fee() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture
+        .initialize_trading(2 * LAMPORTS_PER_SOL, &admin)
+        .await;
+
+    let fee_recipient = Keypair::new().pubkey();
+    let set_fee = |fee_bps| SplStoreInstruction::SetFee {
+        fee_bps,
+        fee_recipient,
+    };
+    let result = fixture
+        .process_as(set_fee(100), &fixture.client.pubkey(), &[])
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+    let result = fixture
+        .process_as(set_fee(10_001), &admin.pubkey(), &[&admin])
+        .await;
+    assert_store_error(result, SplStoreError::InvalidFee);
+    // 1%
+    fixture
+        .process_as(set_fee(100), &admin.pubkey(), &[&admin])
+        .await
+        .unwrap();
+
+    // The client receives the value of the tokens less the fee
+    let result = fixture
+        .trade(SplStoreInstruction::Buy {
+            amount: 2,
+            min_lamports: 4 * LAMPORTS_PER_SOL,
+        })
+        .await;
+    assert_store_error(result, SplStoreError::SlippageExceeded);
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    fixture
+        .trade(SplStoreInstruction::Buy {
+            amount: 2,
+            min_lamports: 3_960_000_000,
+        })
+        .await
+        .unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 3_960_000_000);
+
+    // and pays the value of the tokens plus the fee
+    let result = fixture
+        .trade(SplStoreInstruction::Sell {
+            amount: 1,
+            max_lamports: 2 * LAMPORTS_PER_SOL,
+        })
+        .await;
+    assert_store_error(result, SplStoreError::SlippageExceeded);
+    fixture
+        .trade(SplStoreInstruction::Sell {
+            amount: 1,
+            max_lamports: 2_020_000_000,
+        })
+        .await
+        .unwrap();
+    let vault_lamports = vault_lamports - 3_960_000_000 + 2_020_000_000;
+    assert_eq!(fixture.balance(vault).await, vault_lamports);
+    assert_eq!(fixture.store_account().await.fees_collected, 60_000_000);
+
+    // Fees are not part of the liquidity the treasury can withdraw
+    let destination = Keypair::new().pubkey();
+    let available = vault_lamports - Rent::default().minimum_balance(0) - 60_000_000;
+    let result = fixture
+        .withdraw_sol(available + 1, &destination, &admin)
+        .await;
+    assert_store_error(result, SplStoreError::WithdrawalBreaksRentExemption);
+    let admin_ata = fixture.admin_ata(&admin.pubkey());
+    let result = fixture.close_store(&admin, &admin_ata, &destination).await;
+    assert_store_error(result, SplStoreError::UnclaimedFees);
+
+    // Anyone may pay the fees out, to the fee recipient only
+    let result = fixture.collect_fees(&destination).await;
+    assert_store_error(result, SplStoreError::FeeRecipientMismatch);
+    fixture.collect_fees(&fee_recipient).await.unwrap();
+    assert_eq!(fixture.balance(fee_recipient).await, 60_000_000);
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 60_000_000);
+    assert_eq!(fixture.store_account().await.fees_collected, 0);
+    fixture
+        .withdraw_sol(available, &destination, &admin)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn referrers_share_the_fee() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture
+        .initialize_trading(2 * LAMPORTS_PER_SOL, &admin)
+        .await;
+
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::SetReferralShare(5_000),
+            &fixture.client.pubkey(),
+            &[],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::SetReferralShare(10_001),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::InvalidReferralShare);
+    // Half of a 1% fee
+    for instruction in [
+        SplStoreInstruction::SetReferralShare(5_000),
+        SplStoreInstruction::SetFee {
+            fee_bps: 100,
+            fee_recipient: Keypair::new().pubkey(),
+        },
+    ] {
+        fixture
+            .process_as(instruction, &admin.pubkey(), &[&admin])
+            .await
+            .unwrap();
+    }
+
+    let referrer = Keypair::new().pubkey();
+    let buy = SplStoreInstruction::Buy {
+        amount: 2,
+        min_lamports: 0,
+    };
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    fixture
+        .trade_with_accounts(buy, vec![AccountMeta::new(referrer, false)])
+        .await
+        .unwrap();
+    assert_eq!(fixture.balance(referrer).await, 20_000_000);
+    assert_eq!(
+        fixture.balance(vault).await,
+        vault_lamports - 3_960_000_000 - 20_000_000
+    );
+    assert_eq!(fixture.store_account().await.fees_collected, 20_000_000);
+
+    let sell = SplStoreInstruction::Sell {
+        amount: 1,
+        max_lamports: u64::MAX,
+    };
+    fixture
+        .trade_with_accounts(sell, vec![AccountMeta::new(referrer, false)])
+        .await
+        .unwrap();
+    assert_eq!(fixture.balance(referrer).await, 30_000_000);
+    assert_eq!(fixture.store_account().await.fees_collected, 30_000_000);
+
+    // Without a referrer the store keeps the whole fee, programs passed along are no referrers
+    fixture
+        .trade_with_accounts(
+            SplStoreInstruction::Sell {
+                amount: 1,
+                max_lamports: u64::MAX,
+            },
+            vec![AccountMeta::new_readonly(
+                spl_associated_token_account::id(),
+                false,
+            )],
+        )
+        .await
+        .unwrap();
+    assert_eq!(fixture.balance(referrer).await, 30_000_000);
+    assert_eq!(fixture.store_account().await.fees_collected, 50_000_000);
+
+    let result = fixture
+        .trade_with_accounts(
+            SplStoreInstruction::Buy {
+                amount: 1,
+                min_lamports: 0,
+            },
+            vec![AccountMeta::new_readonly(referrer, false)],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotWritable);
+    // Neither the client nor the funding account can refer its own trade
+    let client = fixture.client.pubkey();
+    let payer = fixture.payer.pubkey();
+    for self_referrer in [client, payer] {
+        let result = fixture
+            .trade_with_accounts(
+                SplStoreInstruction::Buy {
+                    amount: 1,
+                    min_lamports: 0,
+                },
+                vec![AccountMeta::new(self_referrer, false)],
+            )
+            .await;
+        assert_store_error(result, SplStoreError::InvalidReferrer);
+    }
+
+    // Referrers may follow programs passed along
+    fixture
+        .trade_with_accounts(
+            SplStoreInstruction::Sell {
+                amount: 2,
+                max_lamports: u64::MAX,
+            },
+            vec![
+                AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+                AccountMeta::new(referrer, false),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(fixture.balance(referrer).await, 50_000_000);
+}
+
+#[tokio::test]
+async fn referrers_share_the_fee_in_quote_mint() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize_trading(LAMPORTS_PER_SOL, &admin).await;
+    let quote_mint = fixture.create_mint(6).await.pubkey();
+    // Half of a 1% fee, 2.5 quote tokens per token
+    for instruction in [
+        SplStoreInstruction::SetQuoteMint(Some(quote_mint)),
+        update_price(2_500_000),
+        SplStoreInstruction::SetReferralShare(5_000),
+        SplStoreInstruction::SetFee {
+            fee_bps: 100,
+            fee_recipient: Keypair::new().pubkey(),
+        },
+    ] {
+        fixture
+            .process_as(instruction, &admin.pubkey(), &[&admin])
+            .await
+            .unwrap();
+    }
+
+    let store = fixture.store;
+    let referrer = Keypair::new().pubkey();
+    let quote_ata = |owner| get_associated_token_address(owner, &quote_mint);
+    let store_quote_ata = quote_ata(&store);
+    let referrer_quote_ata = quote_ata(&referrer);
+    for owner in [store, referrer] {
+        let instruction = create_associated_token_account(
+            &fixture.payer.pubkey(),
+            &owner,
+            &quote_mint,
+            &spl_token::id(),
+        );
+        fixture.process(instruction, &[]).await.unwrap();
+    }
+    fixture
+        .mint_tokens_to(&quote_mint, &store_quote_ata, 10_000_000)
+        .await;
+
+    // The referrer follows the ATA program the trade passes along
+    let referrer_accounts = || vec![AccountMeta::new(referrer_quote_ata, false)];
+    fixture
+        .trade_in_quote_mint_with(
+            SplStoreInstruction::Buy {
+                amount: 2,
+                min_lamports: 0,
+            },
+            &quote_mint,
+            referrer_accounts(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(fixture.token_balance(referrer_quote_ata).await, 25_000);
+    assert_eq!(
+        fixture.token_balance(store_quote_ata).await,
+        10_000_000 - 4_950_000 - 25_000
+    );
+    assert_eq!(fixture.store_account().await.fees_collected, 25_000);
+
+    fixture
+        .trade_in_quote_mint_with(
+            SplStoreInstruction::Sell {
+                amount: 1,
+                max_lamports: u64::MAX,
+            },
+            &quote_mint,
+            referrer_accounts(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(fixture.token_balance(referrer_quote_ata).await, 37_500);
+    assert_eq!(
+        fixture.token_balance(store_quote_ata).await,
+        5_025_000 + 2_525_000 - 12_500
+    );
+    assert_eq!(fixture.store_account().await.fees_collected, 37_500);
+
+    // Lamports are no payout in the quote mint
+    let result = fixture
+        .trade_in_quote_mint_with(
+            SplStoreInstruction::Buy {
+                amount: 1,
+                min_lamports: 0,
+            },
+            &quote_mint,
+            vec![AccountMeta::new(referrer, false)],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::NoAccountMint);
+    // nor is the client's own quote ATA
+    let client_quote_ata = quote_ata(&fixture.client.pubkey());
+    let result = fixture
+        .trade_in_quote_mint_with(
+            SplStoreInstruction::Buy {
+                amount: 1,
+                min_lamports: 0,
+            },
+            &quote_mint,
+            vec![AccountMeta::new(client_quote_ata, false)],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::InvalidReferrer);
+}
+
+#[tokio::test]
+async fn bonding_curve_prices_the_store_token() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize_trading(1_000, &admin).await;
+
+    // 1000 lamports per token, 100 more for every token sold
+    let curve = PricingMode::Curve(Curve::Linear {
+        base_price: 1_000,
+        price_step: 100,
+        step_interval: 1,
+    });
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::SetPricingMode(curve),
+            &fixture.client.pubkey(),
+            &[],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::AccountNotAdmin);
+    let result = fixture
+        .process_as(
+            SplStoreInstruction::SetPricingMode(PricingMode::Curve(Curve::Exponential {
+                base_price: 1_000,
+                growth_bps: 0,
+                step_interval: 1,
+            })),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await;
+    assert_store_error(result, SplStoreError::InvalidCurve);
+    fixture
+        .process_as(
+            SplStoreInstruction::SetPricingMode(curve),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    let result = fixture
+        .process_as(update_price(5), &admin.pubkey(), &[&admin])
+        .await;
+    assert_store_error(result, SplStoreError::PricedByCurve);
+
+    // Nothing was sold on the curve yet
+    let result = fixture.buy(1).await;
+    assert_store_error(result, SplStoreError::CurveSupplyExceeded);
+
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    fixture.sell(2).await.unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports + 2_200);
+    fixture.sell(1).await.unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports + 3_450);
+    assert_eq!(fixture.store_account().await.tokens_sold, 3);
+
+    // Switching curves keeps the tokens sold outstanding. The last curve prices like the
+    // first one, setting that again would be a duplicate transaction
+    let steeper = PricingMode::Curve(Curve::Linear {
+        base_price: 1_000,
+        price_step: 200,
+        step_interval: 1,
+    });
+    let same_slope = PricingMode::Curve(Curve::Linear {
+        base_price: 1_000,
+        price_step: 200,
+        step_interval: 2,
+    });
+    for pricing_mode in [steeper, same_slope] {
+        fixture
+            .process_as(
+                SplStoreInstruction::SetPricingMode(pricing_mode),
+                &admin.pubkey(),
+                &[&admin],
+            )
+            .await
+            .unwrap();
+        assert_eq!(fixture.store_account().await.tokens_sold, 3);
+    }
+
+    // Buying back walks the curve down
+    fixture.buy(3).await.unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports);
+    assert_eq!(fixture.store_account().await.tokens_sold, 0);
+    let result = fixture.buy(1).await;
+    assert_store_error(result, SplStoreError::CurveSupplyExceeded);
+
+    fixture
+        .process_as(
+            SplStoreInstruction::SetPricingMode(PricingMode::Fixed),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    fixture
+        .process_as(update_price(5), &admin.pubkey(), &[&admin])
+        .await
+        .unwrap();
+    fixture.buy(1).await.unwrap();
+    assert_eq!(fixture.balance(vault).await, vault_lamports - 5);
+}
+
+#[tokio::test]
+async fn constant_product_prices_from_reserves() {
+    let mut fixture = StoreFixture::new().await;
+    let admin = Keypair::new();
+    fixture.initialize_trading(1, &admin).await;
+    fixture
+        .process_as(
+            SplStoreInstruction::SetPricingMode(PricingMode::ConstantProduct),
+            &admin.pubkey(),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+    let result = fixture
+        .process_as(update_price(5), &admin.pubkey(), &[&admin])
+        .await;
+    assert_store_error(result, SplStoreError::PricedByReserves);
+
+    // The store cannot sell its whole token reserve
+    let result = fixture.sell(100).await;
+    assert_store_error(result, SplStoreError::InsufficientReserves);
+
+    let vault = fixture.vault();
+    let vault_lamports = fixture.balance(vault).await;
+    let sol_reserve = u128::from(vault_lamports - Rent::default().minimum_balance(0));
+    fixture.sell(10).await.unwrap();
+    let paid = (sol_reserve * 10).div_ceil(90) as u64;
+    assert_eq!(fixture.balance(vault).await, vault_lamports + paid);
+
+    // The price fell back with the token reserve refilled
+    fixture.buy(10).await.unwrap();
+    let refunded = ((sol_reserve + u128::from(paid)) * 10 / 100) as u64;
+    assert!(refunded <= paid);
+    assert_eq!(
+        fixture.balance(vault).await,
+        vault_lamports + paid - refunded
+    );
+}